@@ -0,0 +1,169 @@
+use std::path::PathBuf;
+
+pub static HELP: &str = "\
+Usage: ralculator [OPTIONS]
+
+Without options the calculator window is opened. If ralculator is already
+running, the options are passed to the open window instead.
+
+Options:
+  -e, --eval EXPR          Evaluate EXPR and print the result, may be repeated
+      --json               Print results of -e and stdin as JSON
+      --expr EXPR          Evaluate EXPR in the calculator window
+      --mode MODE          Switch the window to MODE, scientific or programmer
+      --history-file PATH  Keep the calculator history in PATH
+  -h, --help               Print this help
+  -V, --version            Print the version
+
+Expressions piped into ralculator are evaluated line by line.
+";
+
+/// Layouts of the calculator window
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Mode {
+    Scientific,
+    /// Integer results are also shown in hexadecimal, octal and binary
+    Programmer,
+}
+
+/// The options `ralculator` was started with
+#[derive(Debug, PartialEq, Default)]
+pub struct Args {
+    /// Expressions evaluated without opening a window
+    pub eval: Vec<String>,
+    pub json: bool,
+    /// Expression evaluated in the calculator window
+    pub expr: Option<String>,
+    pub mode: Option<Mode>,
+    pub history_file: Option<PathBuf>,
+    pub help: bool,
+    pub version: bool,
+    /// Started by D-Bus activation, the option is handled by GApplication
+    pub service: bool,
+}
+
+impl Args {
+    /// Whether any of the options only makes sense for the window
+    pub fn has_window_options(&self) -> bool {
+        self.expr.is_some() || self.mode.is_some() || self.history_file.is_some() || self.service
+    }
+}
+
+/// Parses the arguments without the program name. Values can be given as
+/// separate argument or after `=`, e.g. `--mode=programmer`.
+pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Args, String> {
+    let mut parsed = Args::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let (name, mut value) = match arg.find('=') {
+            Some(i) if arg.starts_with("--") => {
+                (arg[..i].to_string(), Some(arg[i + 1..].to_string()))
+            }
+            _ => (arg.clone(), None),
+        };
+
+        let takes_value = match name.as_str() {
+            "-e" | "--eval" | "--expr" | "--mode" | "--history-file" => true,
+            "--json" | "-h" | "--help" | "-V" | "--version" | "--gapplication-service" => false,
+            _ => return Err(format!("unknown option {}", arg)),
+        };
+
+        if takes_value && value.is_none() {
+            value = args.next();
+        } else if !takes_value && value.is_some() {
+            return Err(format!("{} doesn't take a value", name));
+        }
+
+        let value = || {
+            value
+                .clone()
+                .ok_or_else(|| format!("{} expects a value", name))
+        };
+
+        match name.as_str() {
+            "-e" | "--eval" => parsed.eval.push(value()?),
+            "--expr" => parsed.expr = Some(value()?),
+            "--mode" => {
+                parsed.mode = Some(match value()?.as_str() {
+                    "scientific" => Mode::Scientific,
+                    "programmer" => Mode::Programmer,
+                    mode => {
+                        return Err(format!(
+                            "unknown mode {}, expected scientific or programmer",
+                            mode
+                        ))
+                    }
+                })
+            }
+            "--history-file" => parsed.history_file = Some(PathBuf::from(value()?)),
+            "--json" => parsed.json = true,
+            "-h" | "--help" => parsed.help = true,
+            "--gapplication-service" => parsed.service = true,
+            _ => parsed.version = true,
+        }
+    }
+
+    Ok(parsed)
+}
+
+#[cfg(test)]
+fn parse_str(args: &str) -> Result<Args, String> {
+    parse(args.split_whitespace().map(String::from))
+}
+
+#[test]
+fn test_parse_args() {
+    assert_eq!(parse_str(""), Ok(Args::default()));
+    assert_eq!(
+        parse_str("-e 1 --eval=2 --json"),
+        Ok(Args {
+            eval: vec![String::from("1"), String::from("2")],
+            json: true,
+            ..Args::default()
+        })
+    );
+    assert_eq!(
+        parse_str("--expr=2^10 --mode programmer --history-file /tmp/history"),
+        Ok(Args {
+            expr: Some(String::from("2^10")),
+            mode: Some(Mode::Programmer),
+            history_file: Some(PathBuf::from("/tmp/history")),
+            ..Args::default()
+        })
+    );
+    assert_eq!(
+        parse_str("-V --help"),
+        Ok(Args {
+            help: true,
+            version: true,
+            ..Args::default()
+        })
+    );
+    assert_eq!(
+        parse_str("--mode=scientific").map(|args| args.mode),
+        Ok(Some(Mode::Scientific))
+    );
+    assert!(parse_str("--gapplication-service")
+        .unwrap()
+        .has_window_options());
+}
+
+#[test]
+fn test_parse_args_errors() {
+    assert_eq!(
+        parse_str("--expr"),
+        Err(String::from("--expr expects a value"))
+    );
+    assert_eq!(
+        parse_str("--mode hex"),
+        Err(String::from(
+            "unknown mode hex, expected scientific or programmer"
+        ))
+    );
+    assert_eq!(
+        parse_str("--json=1"),
+        Err(String::from("--json doesn't take a value"))
+    );
+    assert_eq!(parse_str("foo"), Err(String::from("unknown option foo")));
+}
@@ -0,0 +1,173 @@
+use std::io::{self, BufRead, Write};
+
+use ralculator::{diagnose, parse_and_execute, Context, InputError};
+
+use json::Output;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Format {
+    Text,
+    /// One JSON object per evaluated line, errors included, see `json::Output`
+    Json,
+}
+
+/// Evaluates one expression, the result is written to `out` and all errors
+/// are written to `err` prefixed with `source:line:column`. In the JSON format
+/// both are written to `out`. Returns whether the evaluation succeeded.
+pub fn evaluate<W: Write, E: Write>(
+    source: &str,
+    line: usize,
+    input: &str,
+    context: &Context,
+    format: Format,
+    out: &mut W,
+    err: &mut E,
+) -> io::Result<bool> {
+    let result = parse_and_execute(input, context);
+
+    if format == Format::Json {
+        let success = result.is_ok();
+        writeln!(out, "{}", Output::new(line, input, result).to_json())?;
+        return Ok(success);
+    }
+
+    match result {
+        Ok(value) => {
+            writeln!(out, "{}", value)?;
+            Ok(true)
+        }
+        Err(_) => {
+            for InputError { msg, pos } in diagnose(input, context) {
+                let line = line + pos.start.line - 1;
+                let column = pos.start.column + 1;
+                writeln!(err, "{}:{}:{}: error: {}", source, line, column, msg)?;
+                writeln!(err, "{}", pos.start.line_text(input))?;
+                writeln!(err, "{}{}", " ".repeat(pos.start.column), pos.marker(input))?;
+            }
+            Ok(false)
+        }
+    }
+}
+
+/// Evaluates every line as separate expression, empty lines and lines
+/// starting with `#` are skipped. Returns whether all lines were evaluated
+/// successfully.
+pub fn evaluate_lines<R: BufRead, W: Write, E: Write>(
+    source: &str,
+    input: R,
+    context: &Context,
+    format: Format,
+    out: &mut W,
+    err: &mut E,
+) -> io::Result<bool> {
+    let mut success = true;
+
+    for (i, line) in input.lines().enumerate() {
+        let line = line?;
+        let trimmed = line.trim();
+
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        success &= evaluate(source, i + 1, &line, context, format, out, err)?;
+    }
+
+    Ok(success)
+}
+
+#[cfg(test)]
+fn evaluate_text(text: &str, format: Format) -> (bool, String, String) {
+    let mut out = Vec::new();
+    let mut err = Vec::new();
+    let success = evaluate_lines(
+        "<stdin>",
+        text.as_bytes(),
+        &Context::new(),
+        format,
+        &mut out,
+        &mut err,
+    )
+    .unwrap();
+
+    (
+        success,
+        String::from_utf8(out).unwrap(),
+        String::from_utf8(err).unwrap(),
+    )
+}
+
+#[test]
+fn test_evaluate_lines() {
+    assert_eq!(
+        evaluate_text("2^10\n\n# comment\n1 + 2\n", Format::Text),
+        (true, String::from("1024\n3\n"), String::new())
+    );
+}
+
+#[test]
+fn test_evaluate_lines_reports_errors() {
+    assert_eq!(
+        evaluate_text("1 + 1\n2 + * 3\nfoo\n4", Format::Text),
+        (
+            false,
+            String::from("2\n4\n"),
+            String::from(
+                "<stdin>:2:5: error: expected (, identifier or number\n\
+                 2 + * 3\n    ^\n\
+                 <stdin>:3:1: error: unknown identifier foo\n\
+                 foo\n^^^\n"
+            )
+        )
+    );
+}
+
+#[test]
+fn test_evaluate_lines_continues_after_overflow() {
+    assert_eq!(
+        evaluate_text("21!\n200!\n2 * 3", Format::Text),
+        (
+            false,
+            String::from("51090942171709440000\n6\n"),
+            String::from("<stdin>:2:1: error: 200! is too large\n200!\n^^^^\n")
+        )
+    );
+}
+
+#[test]
+fn test_evaluate_lines_aligns_unicode_errors() {
+    let (_, _, err) = evaluate_text("2 * π × 2", Format::Text);
+    assert_eq!(
+        err,
+        "<stdin>:1:7: error: × is not an operator, did you mean *?\n\
+         2 * π × 2\n      ^\n"
+    );
+}
+
+#[test]
+fn test_evaluate_lines_reports_all_errors() {
+    let (_, _, err) = evaluate_text("2 × 3 + * 4", Format::Text);
+    assert_eq!(
+        err,
+        "<stdin>:1:3: error: × is not an operator, did you mean *?\n\
+         2 × 3 + * 4\n  ^\n\
+         <stdin>:1:9: error: expected (, identifier or number\n\
+         2 × 3 + * 4\n        ^\n"
+    );
+}
+
+#[test]
+fn test_evaluate_lines_json() {
+    assert_eq!(
+        evaluate_text("1 + 1\n\nfoo\n", Format::Json),
+        (
+            false,
+            String::from(
+                "{\"line\":1,\"input\":\"1 + 1\",\"type\":\"number\",\"value\":2,\"unit\":null,\"formatted\":\"2\"}\n\
+                 {\"line\":3,\"input\":\"foo\",\"type\":\"error\",\
+                 \"error\":{\"message\":\"unknown identifier foo\",\"start\":0,\"end\":3}}\n"
+            ),
+            String::new()
+        )
+    );
+}
@@ -0,0 +1,471 @@
+use std::io::{self, Write};
+
+use libc;
+
+use ralculator::display_width;
+
+/// Milliseconds to wait for the rest of an escape sequence, without it Esc
+/// was pressed on its own
+const ESCAPE_TIMEOUT: libc::c_int = 50;
+
+/// The outcome of reading a line
+pub enum ReadResult {
+    Line(String),
+    /// The user pressed Ctrl+C
+    Interrupted,
+    /// The input ended, e.g. the user pressed Ctrl+D on an empty line
+    Eof,
+}
+
+/// Reads lines from stdin, when stdin is a terminal the line can be edited
+/// and previous lines can be recalled with the arrow keys
+pub struct Editor {
+    history: Vec<String>,
+    interactive: bool,
+}
+
+impl Editor {
+    pub fn new(history: Vec<String>) -> Editor {
+        Editor {
+            history,
+            interactive: is_terminal(libc::STDIN_FILENO),
+        }
+    }
+
+    pub fn is_interactive(&self) -> bool {
+        self.interactive
+    }
+
+    #[cfg(test)]
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    /// Appends a line to the history, repeated lines are only stored once.
+    /// Returns whether the line was added.
+    pub fn add_history(&mut self, line: &str) -> bool {
+        if line.is_empty() || self.history.last().map(String::as_str) == Some(line) {
+            return false;
+        }
+
+        self.history.push(line.to_string());
+        true
+    }
+
+    pub fn read_line(&mut self, prompt: &str) -> io::Result<ReadResult> {
+        if !self.interactive {
+            let mut line = String::new();
+            return match io::stdin().read_line(&mut line)? {
+                0 => Ok(ReadResult::Eof),
+                _ => Ok(ReadResult::Line(
+                    line.trim_end_matches(&['\r', '\n'][..]).to_string(),
+                )),
+            };
+        }
+
+        let _raw_mode = RawMode::enable()?;
+        let result = self.edit_line(prompt);
+        print!("\r\n");
+        io::stdout().flush()?;
+        result
+    }
+
+    fn edit_line(&mut self, prompt: &str) -> io::Result<ReadResult> {
+        let mut buffer = LineBuffer::new();
+        // Position in the history while walking through it and the line
+        // that was being typed before
+        let mut history_index = self.history.len();
+        let mut draft = String::new();
+
+        loop {
+            render(prompt, &buffer)?;
+
+            match read_key()? {
+                Key::Enter => return Ok(ReadResult::Line(buffer.text())),
+                Key::Interrupt => {
+                    print!("^C");
+                    return Ok(ReadResult::Interrupted);
+                }
+                Key::EndOfFile if buffer.is_empty() => return Ok(ReadResult::Eof),
+                Key::EndOfFile | Key::Delete => buffer.delete(),
+                Key::Char(c) => buffer.insert(c),
+                Key::Backspace => buffer.backspace(),
+                Key::DeleteWord => buffer.delete_word(),
+                Key::DeleteToStart => buffer.delete_to_start(),
+                Key::DeleteToEnd => buffer.delete_to_end(),
+                Key::Left => buffer.move_left(),
+                Key::Right => buffer.move_right(),
+                Key::Home => buffer.move_home(),
+                Key::End => buffer.move_end(),
+                Key::Up if history_index > 0 => {
+                    if history_index == self.history.len() {
+                        draft = buffer.text();
+                    }
+                    history_index -= 1;
+                    buffer.set_text(&self.history[history_index]);
+                }
+                Key::Down if history_index < self.history.len() => {
+                    history_index += 1;
+                    match self.history.get(history_index) {
+                        Some(line) => buffer.set_text(line),
+                        None => buffer.set_text(&draft),
+                    }
+                }
+                Key::Up | Key::Down | Key::Unknown => {}
+            }
+        }
+    }
+}
+
+fn render(prompt: &str, buffer: &LineBuffer) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    // Redraw the whole line and clear everything right of it
+    write!(stdout, "\r{}{}\x1b[K", prompt, buffer.text())?;
+
+    // Wide characters like `π` in some fonts or CJK take more than one column
+    let back = display_width(&buffer.text_after_cursor());
+    if back > 0 {
+        write!(stdout, "\x1b[{}D", back)?;
+    }
+    stdout.flush()
+}
+
+enum Key {
+    Char(char),
+    Enter,
+    Backspace,
+    Delete,
+    DeleteWord,
+    DeleteToStart,
+    DeleteToEnd,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    Interrupt,
+    EndOfFile,
+    Unknown,
+}
+
+/// Reads from the file descriptor, `io::Stdin` would buffer bytes that
+/// `byte_within` is waiting for
+fn read_byte() -> io::Result<u8> {
+    let mut byte = 0u8;
+    loop {
+        let read = unsafe { libc::read(libc::STDIN_FILENO, &mut byte as *mut u8 as *mut _, 1) };
+        match read {
+            1 => return Ok(byte),
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            _ => {
+                let err = io::Error::last_os_error();
+                if err.kind() != io::ErrorKind::Interrupted {
+                    return Err(err);
+                }
+            }
+        }
+    }
+}
+
+/// The next byte if it arrives within `timeout` milliseconds
+fn byte_within(timeout: libc::c_int) -> io::Result<Option<u8>> {
+    let mut poll_fd = libc::pollfd {
+        fd: libc::STDIN_FILENO,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+
+    match unsafe { libc::poll(&mut poll_fd, 1, timeout) } {
+        -1 => Err(io::Error::last_os_error()),
+        0 => Ok(None),
+        _ => read_byte().map(Some),
+    }
+}
+
+fn read_key() -> io::Result<Key> {
+    let key = match read_byte()? {
+        b'\r' | b'\n' => Key::Enter,
+        0x7f | 0x08 => Key::Backspace,
+        0x01 => Key::Home,
+        0x02 => Key::Left,
+        0x03 => Key::Interrupt,
+        0x04 => Key::EndOfFile,
+        0x05 => Key::End,
+        0x06 => Key::Right,
+        0x0b => Key::DeleteToEnd,
+        0x0e => Key::Down,
+        0x10 => Key::Up,
+        0x15 => Key::DeleteToStart,
+        0x17 => Key::DeleteWord,
+        0x1b => read_escape_sequence()?,
+        byte if byte < 0x20 => Key::Unknown,
+        byte => read_char(byte)?,
+    };
+    Ok(key)
+}
+
+/// Handles the sequences the arrow, home, end and delete keys send. A lone
+/// Esc or Alt with a key is ignored without waiting for the next key.
+fn read_escape_sequence() -> io::Result<Key> {
+    let first = match byte_within(ESCAPE_TIMEOUT)? {
+        Some(byte) => byte,
+        None => return Ok(Key::Unknown),
+    };
+    let second = match byte_within(ESCAPE_TIMEOUT)? {
+        Some(byte) => byte,
+        None => return Ok(Key::Unknown),
+    };
+
+    let key = match (first, second) {
+        (b'[', b'A') | (b'O', b'A') => Key::Up,
+        (b'[', b'B') | (b'O', b'B') => Key::Down,
+        (b'[', b'C') | (b'O', b'C') => Key::Right,
+        (b'[', b'D') | (b'O', b'D') => Key::Left,
+        (b'[', b'H') | (b'O', b'H') => Key::Home,
+        (b'[', b'F') | (b'O', b'F') => Key::End,
+        (b'[', digit) if digit.is_ascii_digit() => {
+            // e.g. `ESC [ 3 ~`, skip any parameters up to the final byte
+            let mut last = digit;
+            let mut code = digit;
+            while !(0x40..=0x7e).contains(&last) {
+                last = read_byte()?;
+                if last.is_ascii_digit() {
+                    code = last;
+                }
+            }
+            match (code, last) {
+                (b'3', b'~') => Key::Delete,
+                (b'1', b'~') | (b'7', b'~') => Key::Home,
+                (b'4', b'~') | (b'8', b'~') => Key::End,
+                _ => Key::Unknown,
+            }
+        }
+        _ => Key::Unknown,
+    };
+    Ok(key)
+}
+
+/// Reads the remaining bytes of a UTF-8 encoded character
+fn read_char(first: u8) -> io::Result<Key> {
+    let length = match first {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => 1,
+    };
+
+    let mut bytes = vec![first];
+    for _ in 1..length {
+        bytes.push(read_byte()?);
+    }
+
+    Ok(match String::from_utf8(bytes) {
+        Ok(text) => text.chars().next().map_or(Key::Unknown, Key::Char),
+        Err(_) => Key::Unknown,
+    })
+}
+
+pub fn is_terminal(fd: libc::c_int) -> bool {
+    unsafe { libc::isatty(fd) == 1 }
+}
+
+/// Turns off line buffering and echoing of the terminal while it is alive
+struct RawMode {
+    original: libc::termios,
+}
+
+impl RawMode {
+    fn enable() -> io::Result<RawMode> {
+        unsafe {
+            let mut original: libc::termios = ::std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut original) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let mut raw = original;
+            raw.c_iflag &= !(libc::ICRNL | libc::IXON);
+            raw.c_lflag &= !(libc::ECHO | libc::ICANON | libc::IEXTEN | libc::ISIG);
+            raw.c_cc[libc::VMIN] = 1;
+            raw.c_cc[libc::VTIME] = 0;
+
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSAFLUSH, &raw) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            Ok(RawMode { original })
+        }
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSAFLUSH, &self.original);
+        }
+    }
+}
+
+/// The line being edited and the position of the cursor in it
+struct LineBuffer {
+    chars: Vec<char>,
+    cursor: usize,
+}
+
+impl LineBuffer {
+    fn new() -> LineBuffer {
+        LineBuffer {
+            chars: Vec::new(),
+            cursor: 0,
+        }
+    }
+
+    fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    fn set_text(&mut self, text: &str) {
+        self.chars = text.chars().collect();
+        self.cursor = self.chars.len();
+    }
+
+    fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+
+    #[cfg(test)]
+    fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    fn text_after_cursor(&self) -> String {
+        self.chars[self.cursor..].iter().collect()
+    }
+
+    fn insert(&mut self, c: char) {
+        self.chars.insert(self.cursor, c);
+        self.cursor += 1;
+    }
+
+    fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.chars.remove(self.cursor);
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.cursor < self.chars.len() {
+            self.chars.remove(self.cursor);
+        }
+    }
+
+    /// Deletes the word left of the cursor and the whitespace after it
+    fn delete_word(&mut self) {
+        let mut start = self.cursor;
+        while start > 0 && self.chars[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        while start > 0 && !self.chars[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        self.chars.drain(start..self.cursor);
+        self.cursor = start;
+    }
+
+    fn delete_to_start(&mut self) {
+        self.chars.drain(..self.cursor);
+        self.cursor = 0;
+    }
+
+    fn delete_to_end(&mut self) {
+        self.chars.truncate(self.cursor);
+    }
+
+    fn move_left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    fn move_right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.chars.len());
+    }
+
+    fn move_home(&mut self) {
+        self.cursor = 0;
+    }
+
+    fn move_end(&mut self) {
+        self.cursor = self.chars.len();
+    }
+}
+
+#[cfg(test)]
+fn buffer_with(text: &str) -> LineBuffer {
+    let mut buffer = LineBuffer::new();
+    buffer.set_text(text);
+    buffer
+}
+
+#[test]
+fn test_insert_at_cursor() {
+    let mut buffer = buffer_with("2 + 3");
+    buffer.move_home();
+    buffer.insert('π');
+    buffer.move_end();
+    buffer.insert('!');
+    assert_eq!(buffer.text(), "π2 + 3!");
+    assert_eq!(buffer.cursor(), 7);
+}
+
+#[test]
+fn test_text_after_cursor() {
+    let mut buffer = buffer_with("日本 + 1");
+    buffer.move_home();
+    buffer.move_right();
+    assert_eq!(buffer.text_after_cursor(), "本 + 1");
+    assert_eq!(display_width(&buffer.text_after_cursor()), 6);
+}
+
+#[test]
+fn test_backspace_and_delete() {
+    let mut buffer = buffer_with("sin(x)");
+    buffer.backspace();
+    buffer.move_home();
+    buffer.delete();
+    assert_eq!(buffer.text(), "in(x");
+
+    let mut empty = LineBuffer::new();
+    empty.backspace();
+    empty.delete();
+    assert!(empty.is_empty());
+}
+
+#[test]
+fn test_delete_word() {
+    let mut buffer = buffer_with("2 * foo  ");
+    buffer.delete_word();
+    assert_eq!(buffer.text(), "2 * ");
+    buffer.delete_word();
+    assert_eq!(buffer.text(), "2 ");
+}
+
+#[test]
+fn test_delete_to_start_and_end() {
+    let mut buffer = buffer_with("1 + 2");
+    buffer.move_left();
+    buffer.move_left();
+    buffer.delete_to_end();
+    assert_eq!(buffer.text(), "1 +");
+    buffer.move_left();
+    buffer.delete_to_start();
+    assert_eq!((buffer.text(), buffer.cursor()), (String::from("+"), 0));
+}
+
+#[test]
+fn test_add_history_skips_repetitions() {
+    let mut editor = Editor::new(vec![String::from("1 + 1")]);
+    assert!(!editor.add_history("1 + 1"));
+    assert!(!editor.add_history(""));
+    assert!(editor.add_history("2 * 2"));
+    assert_eq!(editor.history(), &["1 + 1", "2 * 2"]);
+}
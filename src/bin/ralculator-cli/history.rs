@@ -0,0 +1,87 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use ralculator::data_dir;
+
+/// Number of lines kept in the history file
+const MAX_ENTRIES: usize = 1000;
+
+/// `cli_history` in the data directory of ralculator
+pub fn default_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("cli_history"))
+}
+
+/// Reads the history, a missing file is an empty history
+pub fn load(path: &Path) -> io::Result<Vec<String>> {
+    match File::open(path) {
+        Ok(file) => BufReader::new(file).lines().collect(),
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Reads the history like `load` and rewrites the file if it has more than
+/// `MAX_ENTRIES`, as `append` lets it grow
+pub fn load_trimmed(path: &Path) -> io::Result<Vec<String>> {
+    let mut history = load(path)?;
+
+    if history.len() > MAX_ENTRIES {
+        let excess = history.len() - MAX_ENTRIES;
+        history.drain(..excess);
+        save(path, &history)?;
+    }
+    Ok(history)
+}
+
+/// Adds an entry to the end of the history file, so it isn't lost if the
+/// REPL doesn't exit cleanly
+pub fn append(path: &Path, entry: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", entry)
+}
+
+/// Writes the most recent entries of the history, one per line
+pub fn save(path: &Path, history: &[String]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut file = File::create(path)?;
+    let skip = history.len().saturating_sub(MAX_ENTRIES);
+    for entry in &history[skip..] {
+        writeln!(file, "{}", entry)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_save_and_load() {
+    let path = ::std::env::temp_dir()
+        .join(format!("ralculator-test-{}", ::std::process::id()))
+        .join("cli_history");
+
+    assert_eq!(load(&path).unwrap(), Vec::<String>::new());
+
+    let history: Vec<String> = (0..MAX_ENTRIES + 5).map(|i| i.to_string()).collect();
+    save(&path, &history).unwrap();
+
+    let loaded = load(&path).unwrap();
+    assert_eq!(loaded.len(), MAX_ENTRIES);
+    assert_eq!(loaded[0], "5");
+
+    append(&path, "2^10").unwrap();
+    assert_eq!(load(&path).unwrap().len(), MAX_ENTRIES + 1);
+
+    let trimmed = load_trimmed(&path).unwrap();
+    assert_eq!(trimmed.len(), MAX_ENTRIES);
+    assert_eq!(trimmed[0], "6");
+    assert_eq!(trimmed[MAX_ENTRIES - 1], "2^10");
+    assert_eq!(load(&path).unwrap(), trimmed);
+
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
@@ -0,0 +1,164 @@
+extern crate libc;
+extern crate ralculator;
+
+mod editor;
+mod history;
+
+use ralculator::{diagnose, parse_and_execute, Context, InputError};
+
+use editor::{is_terminal, Editor, ReadResult};
+
+const PROMPT: &str = "> ";
+/// Shown while the input continues on the next line
+const CONTINUATION_PROMPT: &str = ". ";
+
+static HELP: &str = "\
+Enter an expression to evaluate it, e.g. `2^10` or `sin(30deg)`.
+
+Input continues on the next line while parentheses are open or the line
+ends with `\\`. Use the arrow keys to edit the line and walk through the
+history, Ctrl+C discards the input and Ctrl+D or `exit` quits.";
+
+/// ANSI escape codes used when writing to a terminal
+struct Style {
+    result: &'static str,
+    error: &'static str,
+    reset: &'static str,
+}
+
+static COLORED: Style = Style {
+    result: "\x1b[1m",
+    error: "\x1b[1;31m",
+    reset: "\x1b[0m",
+};
+
+static PLAIN: Style = Style {
+    result: "",
+    error: "",
+    reset: "",
+};
+
+fn main() {
+    let history_path = history::default_path();
+    let history = match history_path {
+        Some(ref path) => history::load_trimmed(path).unwrap_or_else(|err| {
+            eprintln!("Could not read history {}: {}", path.display(), err);
+            Vec::new()
+        }),
+        None => Vec::new(),
+    };
+
+    let mut editor = Editor::new(history);
+    let style = if use_color() { &COLORED } else { &PLAIN };
+    let context = Context::new();
+    let mut input = String::new();
+
+    loop {
+        let prompt = match (editor.is_interactive(), input.is_empty()) {
+            (false, _) => "",
+            (true, true) => PROMPT,
+            (true, false) => CONTINUATION_PROMPT,
+        };
+
+        let line = match editor.read_line(prompt) {
+            Ok(ReadResult::Line(line)) => line,
+            Ok(ReadResult::Interrupted) => {
+                input.clear();
+                continue;
+            }
+            Ok(ReadResult::Eof) => break,
+            Err(err) => {
+                eprintln!("Could not read input: {}", err);
+                break;
+            }
+        };
+
+        let continued = !input.is_empty();
+        if continued {
+            input.push(' ');
+        }
+
+        if line.ends_with('\\') {
+            input.push_str(&line[..line.len() - 1]);
+            continue;
+        }
+        input.push_str(&line);
+        if has_open_parentheses(&input) {
+            continue;
+        }
+
+        match input.trim() {
+            "" => {}
+            "exit" | "quit" => break,
+            "help" => println!("{}", HELP),
+            trimmed => {
+                if editor.add_history(trimmed) {
+                    if let Some(ref path) = history_path {
+                        if let Err(err) = history::append(path, trimmed) {
+                            eprintln!("Could not write history {}: {}", path.display(), err);
+                        }
+                    }
+                }
+
+                // The markers can only be put under the input if it is the
+                // line right above them
+                let marker_offset = if editor.is_interactive() && !continued {
+                    Some(PROMPT.len())
+                } else {
+                    None
+                };
+                print_result(&input, &context, marker_offset, style);
+            }
+        }
+
+        input.clear();
+    }
+}
+
+/// Prints the result or all errors of the input, each with a marker under
+/// the erroneous part
+fn print_result(input: &str, context: &Context, marker_offset: Option<usize>, style: &Style) {
+    if let Ok(value) = parse_and_execute(input, context) {
+        println!("{}= {}{}", style.result, value, style.reset);
+        return;
+    }
+
+    for (i, InputError { msg, pos }) in diagnose(input, context).into_iter().enumerate() {
+        let offset = match marker_offset {
+            Some(offset) if i == 0 => offset,
+            _ => {
+                println!("{}", pos.start.line_text(input));
+                0
+            }
+        };
+
+        println!(
+            "{}{}{}{}",
+            " ".repeat(offset + pos.start.column),
+            style.error,
+            pos.marker(input),
+            style.reset
+        );
+        println!("{}error:{} {}", style.error, style.reset, msg);
+    }
+}
+
+/// Colors are used when writing to a terminal, unless `NO_COLOR` is set
+fn use_color() -> bool {
+    is_terminal(libc::STDOUT_FILENO)
+        && ::std::env::var_os("NO_COLOR").is_none()
+        && ::std::env::var("TERM").map_or(true, |term| term != "dumb")
+}
+
+fn has_open_parentheses(input: &str) -> bool {
+    let open = input.chars().filter(|c| *c == '(').count();
+    let close = input.chars().filter(|c| *c == ')').count();
+    open > close
+}
+
+#[test]
+fn test_has_open_parentheses() {
+    assert!(has_open_parentheses("sin(1 + (2"));
+    assert!(!has_open_parentheses("sin(1 + (2))"));
+    assert!(!has_open_parentheses("1)"));
+}
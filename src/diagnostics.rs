@@ -0,0 +1,312 @@
+//! Explanations for common mistakes, which are more helpful than the list of
+//! expected tokens
+
+use std::collections::HashSet;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum TokenKind {
+    Number,
+    Identifier,
+    Symbol(char),
+}
+
+/// A rough token of the input, only used to describe errors
+#[derive(Debug, PartialEq, Copy, Clone)]
+struct Token<'a> {
+    kind: TokenKind,
+    text: &'a str,
+    /// Byte offset of the first character
+    start: usize,
+}
+
+impl<'a> Token<'a> {
+    fn end(&self) -> usize {
+        self.start + self.text.len()
+    }
+
+    fn is_name(&self) -> bool {
+        self.kind == TokenKind::Number || self.kind == TokenKind::Identifier
+    }
+
+    /// Whether the token can start an operand
+    fn starts_operand(&self) -> bool {
+        self.is_name() || self.kind == TokenKind::Symbol('(')
+    }
+
+    /// Whether the token can end an operand
+    fn ends_operand(&self) -> bool {
+        match self.kind {
+            TokenKind::Number | TokenKind::Identifier => true,
+            TokenKind::Symbol(c) => c == ')' || c == '!' || c == '°',
+        }
+    }
+}
+
+/// An explanation and the byte range of the input it refers to
+pub type Diagnostic = (String, usize, usize);
+
+/// Explains a parse error at the byte `offset` of `input`, `None` if the
+/// mistake isn't a common one
+pub fn explain_parse_error(
+    input: &str,
+    offset: usize,
+    expected: &HashSet<&str>,
+) -> Option<Diagnostic> {
+    let tokens = tokenize(input);
+    let next = tokens.iter().find(|token| token.start == offset);
+    let before: Vec<&Token> = tokens
+        .iter()
+        .filter(|token| token.end() <= offset)
+        .collect();
+    let (unclosed, unmatched) = unbalanced_parentheses(input);
+
+    let next = match next {
+        Some(next) => next,
+        None => {
+            // The input ended before a parenthesis was closed
+            return match unclosed {
+                Some(start) if expected.contains(")") => {
+                    Some((String::from("unclosed ("), start, start + 1))
+                }
+                _ => None,
+            };
+        }
+    };
+
+    if let TokenKind::Symbol(c) = next.kind {
+        let message = match c {
+            ')' if unmatched == Some(next.start) => String::from("unmatched )"),
+            '×' | '·' => format!("{} is not an operator, did you mean *?", c),
+            '÷' => format!("{} is not an operator, did you mean /?", c),
+            _ => return None,
+        };
+
+        return Some((message, next.start, next.end()));
+    }
+
+    let previous = match before.last() {
+        Some(previous) if next.starts_operand() && previous.ends_operand() => previous,
+        _ => return None,
+    };
+
+    // `2 x 3` is parsed as `2x`, which is followed by `3`
+    if previous.text == "x" && before.len() >= 2 && before[before.len() - 2].ends_operand() {
+        return Some((
+            String::from("x is not an operator, did you mean *?"),
+            previous.start,
+            previous.end(),
+        ));
+    }
+
+    let message = if previous.is_name() && next.is_name() {
+        format!(
+            "operator missing between {} and {}",
+            previous.text, next.text
+        )
+    } else {
+        format!("operator missing before {}", next.text)
+    };
+
+    Some((message, next.start, next.end()))
+}
+
+/// The byte range of the identifier `name` in the input
+pub fn find_identifier(input: &str, name: &str) -> Option<(usize, usize)> {
+    tokenize(input)
+        .into_iter()
+        .find(|token| token.kind == TokenKind::Identifier && token.text == name)
+        .map(|token| (token.start, token.end()))
+}
+
+/// The candidate that is most similar to `name`, if any is similar enough
+/// to be a typo
+pub fn suggest<'a, I: IntoIterator<Item = &'a str>>(name: &str, candidates: I) -> Option<&'a str> {
+    let len = name.chars().count();
+    if len < 2 {
+        return None;
+    }
+    let max_distance = if len < 6 { 1 } else { 2 };
+
+    candidates
+        .into_iter()
+        .filter(|&candidate| candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|&(distance, _)| distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// The number of insertions, deletions, substitutions and transpositions of
+/// adjacent characters needed to turn `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // `distances[i][j]` is the distance between the first i characters of
+    // `a` and the first j characters of `b`
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitution = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + substitution);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+
+    distances[a.len()][b.len()]
+}
+
+fn tokenize<'a>(input: &'a str) -> Vec<Token<'a>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let kind = match c {
+            '0'..='9' => TokenKind::Number,
+            'a'..='z' | 'A'..='Z' | '_' | 'π' | '∞' => TokenKind::Identifier,
+            '\u{180E}' | '\u{FEFF}' => continue,
+            c if c.is_whitespace() => continue,
+            c => TokenKind::Symbol(c),
+        };
+
+        let mut end = start + c.len_utf8();
+        // π and ∞ are identifiers on their own
+        if c != 'π' && c != '∞' {
+            while let Some(&(i, c)) = chars.peek() {
+                let continues = match kind {
+                    TokenKind::Number => c.is_ascii_digit() || c == '.',
+                    TokenKind::Identifier => c.is_ascii_alphanumeric() || c == '_',
+                    TokenKind::Symbol(_) => false,
+                };
+                if !continues {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+        }
+
+        tokens.push(Token {
+            kind,
+            text: &input[start..end],
+            start,
+        });
+    }
+
+    tokens
+}
+
+/// The byte offsets of the last unclosed `(` and of the first `)` without
+/// an opening one
+fn unbalanced_parentheses(input: &str) -> (Option<usize>, Option<usize>) {
+    let mut open = Vec::new();
+    let mut unmatched = None;
+
+    for (i, c) in input.char_indices() {
+        match c {
+            '(' => open.push(i),
+            ')' if open.pop().is_none() && unmatched.is_none() => unmatched = Some(i),
+            _ => {}
+        }
+    }
+
+    (open.pop(), unmatched)
+}
+
+#[cfg(test)]
+fn explain(input: &str) -> Option<(String, &str)> {
+    let err = ::parser::parse(input).unwrap_err();
+    explain_parse_error(input, err.offset, &err.expected)
+        .map(|(message, start, end)| (message, &input[start..end]))
+}
+
+#[test]
+fn test_unclosed_parenthesis() {
+    assert_eq!(
+        explain("2 * (1 + (2) * 3"),
+        Some((String::from("unclosed ("), "("))
+    );
+    assert_eq!(explain("(1 + 2"), Some((String::from("unclosed ("), "(")));
+    // The operand is missing, not the parenthesis
+    assert_eq!(explain("(1 +"), None);
+}
+
+#[test]
+fn test_unmatched_parenthesis() {
+    assert_eq!(
+        explain("2*(3 + 4))"),
+        Some((String::from("unmatched )"), ")"))
+    );
+    assert_eq!(explain("(1 +)"), None);
+}
+
+#[test]
+fn test_multiplication_sign() {
+    assert_eq!(
+        explain("2 × 3"),
+        Some((String::from("× is not an operator, did you mean *?"), "×"))
+    );
+    assert_eq!(
+        explain("6 ÷ 3"),
+        Some((String::from("÷ is not an operator, did you mean /?"), "÷"))
+    );
+    assert_eq!(
+        explain("2 x 3"),
+        Some((String::from("x is not an operator, did you mean *?"), "x"))
+    );
+}
+
+#[test]
+fn test_missing_operator() {
+    assert_eq!(
+        explain("2 3"),
+        Some((String::from("operator missing between 2 and 3"), "3"))
+    );
+    assert_eq!(
+        explain("sin(30) 4.5"),
+        Some((String::from("operator missing before 4.5"), "4.5"))
+    );
+    assert_eq!(explain("2 + * 3"), None);
+}
+
+#[test]
+fn test_suggest() {
+    let names = ["pi", "e", "inf", "sin", "sqrt", "integrate"];
+    assert_eq!(suggest("pu", names.iter().cloned()), Some("pi"));
+    assert_eq!(suggest("sni", names.iter().cloned()), Some("sin"));
+    assert_eq!(suggest("sqr", names.iter().cloned()), Some("sqrt"));
+    assert_eq!(
+        suggest("intgrate", names.iter().cloned()),
+        Some("integrate")
+    );
+    assert_eq!(suggest("y", names.iter().cloned()), None);
+    assert_eq!(suggest("foo", names.iter().cloned()), None);
+}
+
+#[test]
+fn test_edit_distance() {
+    assert_eq!(edit_distance("sin", "sin"), 0);
+    assert_eq!(edit_distance("sni", "sin"), 1);
+    assert_eq!(edit_distance("kitten", "sitting"), 3);
+    assert_eq!(edit_distance("", "abc"), 3);
+}
+
+#[test]
+fn test_find_identifier() {
+    assert_eq!(find_identifier("2x + x2 * x", "x"), Some((1, 2)));
+    assert_eq!(find_identifier("x2 * foo", "foo"), Some((5, 8)));
+    assert_eq!(find_identifier("1 + 2", "x"), None);
+}
@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::fmt;
+
+use super::expression::{constant, CONSTANT_NAMES};
+
+/// The unit angles are interpreted in when they don't carry an explicit unit
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum AngleMode {
+    Degree,
+    Radian,
+    Gradian,
+}
+
+impl AngleMode {
+    /// The mode that follows this one when toggling through all modes
+    pub fn next(self) -> AngleMode {
+        match self {
+            AngleMode::Degree => AngleMode::Radian,
+            AngleMode::Radian => AngleMode::Gradian,
+            AngleMode::Gradian => AngleMode::Degree,
+        }
+    }
+
+    fn full_turn(self) -> f64 {
+        match self {
+            AngleMode::Degree => 360f64,
+            AngleMode::Radian => 2f64 * PI,
+            AngleMode::Gradian => 400f64,
+        }
+    }
+
+    /// Converts an angle given in this unit into an angle in the unit `to`
+    pub fn convert(self, angle: f64, to: AngleMode) -> f64 {
+        if self == to {
+            angle
+        } else {
+            angle / self.full_turn() * to.full_turn()
+        }
+    }
+
+    pub fn to_radians(self, angle: f64) -> f64 {
+        self.convert(angle, AngleMode::Radian)
+    }
+}
+
+impl fmt::Display for AngleMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AngleMode::Degree => write!(f, "DEG"),
+            AngleMode::Radian => write!(f, "RAD"),
+            AngleMode::Gradian => write!(f, "GRAD"),
+        }
+    }
+}
+
+/// State that influences how expressions are evaluated
+#[derive(Debug, Clone)]
+pub struct Context {
+    pub angle_mode: AngleMode,
+    /// Values bound to identifiers, these shadow the built-in constants
+    pub variables: HashMap<String, f64>,
+}
+
+impl Context {
+    pub fn new() -> Context {
+        Context {
+            angle_mode: AngleMode::Degree,
+            variables: HashMap::new(),
+        }
+    }
+
+    /// A copy of this context in which `name` is bound to `value`
+    pub fn with_variable(&self, name: &str, value: f64) -> Context {
+        let mut context = self.clone();
+        context.variables.insert(name.to_string(), value);
+        context
+    }
+
+    /// The built-in constants and the variables, sorted by name
+    pub fn values(&self) -> Vec<(String, f64)> {
+        let mut values: Vec<(String, f64)> = CONSTANT_NAMES
+            .iter()
+            .filter(|name| !self.variables.contains_key(**name))
+            .filter_map(|name| constant(name).map(|value| (name.to_string(), value)))
+            .chain(
+                self.variables
+                    .iter()
+                    .map(|(name, value)| (name.clone(), *value)),
+            )
+            .collect();
+        values.sort_by(|a, b| a.0.cmp(&b.0));
+        values
+    }
+}
+
+impl Default for Context {
+    fn default() -> Context {
+        Context::new()
+    }
+}
+
+#[test]
+fn test_convert_degree_to_radian() {
+    assert_eq!(AngleMode::Degree.convert(180f64, AngleMode::Radian), PI);
+}
+
+#[test]
+fn test_convert_radian_to_gradian() {
+    assert_eq!(AngleMode::Radian.convert(PI, AngleMode::Gradian), 200f64);
+}
+
+#[test]
+fn test_convert_to_same_mode() {
+    assert_eq!(AngleMode::Gradian.convert(42f64, AngleMode::Gradian), 42f64);
+}
+
+#[test]
+fn test_next_cycles_through_all_modes() {
+    assert_eq!(AngleMode::Degree.next().next().next(), AngleMode::Degree);
+}
+
+#[test]
+fn test_values() {
+    use std::f64::consts::E;
+
+    let context = Context::new().with_variable("x", 2.5);
+    assert_eq!(
+        context.values(),
+        vec![
+            (String::from("e"), E),
+            (String::from("inf"), f64::INFINITY),
+            (String::from("pi"), PI),
+            (String::from("x"), 2.5),
+        ]
+    );
+    assert_eq!(
+        context.with_variable("e", 1f64).values()[0],
+        (String::from("e"), 1f64)
+    );
+}
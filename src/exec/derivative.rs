@@ -0,0 +1,302 @@
+use super::context::{AngleMode, Context};
+use super::error::ExecError;
+use super::expression::{contains_variable, execute_expression};
+use super::simplify::simplify;
+use parser::ast::{BinaryOperator, Expression, PrefixOperator, SuffixOperator};
+
+use self::Expression::{BinaryOperation, FunctionCall, Identifier, Number};
+
+/// Handles `diff(f, x)`, returning the simplified derivative of `f` with
+/// respect to `x`
+pub fn symbolic_derivative(
+    args: &[Expression],
+    context: &Context,
+) -> Result<Expression, ExecError> {
+    if args.len() != 2 {
+        return Err(wrong_argument_count(args.len()));
+    }
+
+    let variable = variable_argument(&args[1])?;
+    let derivative = differentiate(&args[0], variable, context.angle_mode)?;
+
+    Ok(simplify(derivative))
+}
+
+/// Handles `diff(f, x, a)`, returning the value of the derivative of `f` at
+/// the point `x = a`
+pub fn evaluate_derivative(args: &[Expression], context: &Context) -> Result<f64, ExecError> {
+    if args.len() == 2 {
+        return Err(ExecError::Symbolic(String::from("diff")));
+    }
+    if args.len() != 3 {
+        return Err(wrong_argument_count(args.len()));
+    }
+
+    let variable = variable_argument(&args[1])?;
+    let point = execute_expression(&args[2], context)?;
+    let derivative = differentiate(&args[0], variable, context.angle_mode)?;
+
+    execute_expression(&derivative, &context.with_variable(variable, point))
+}
+
+fn wrong_argument_count(found: usize) -> ExecError {
+    ExecError::WrongArgumentCount {
+        function: String::from("diff"),
+        expected: if found < 2 { 2 } else { 3 },
+        found,
+    }
+}
+
+fn variable_argument(arg: &Expression) -> Result<&str, ExecError> {
+    match *arg {
+        Identifier(ref name) => Ok(name),
+        _ => Err(ExecError::ExpectedVariable(String::from("diff"))),
+    }
+}
+
+/// Differentiates `expression` with respect to `variable` without simplifying
+/// the result. Trigonometric functions take the angle mode into account, so in
+/// degree mode `sin(x)` becomes `cos(x) * pi / 180`.
+pub fn differentiate(
+    expression: &Expression,
+    variable: &str,
+    mode: AngleMode,
+) -> Result<Expression, ExecError> {
+    let derivative = match *expression {
+        Number(_) => Number(0f64),
+        Identifier(ref name) if name == variable => Number(1f64),
+        Identifier(_) => Number(0f64),
+        FunctionCall(ref name, ref args) => {
+            if args.len() != 1 {
+                return Err(ExecError::NotDifferentiable(expression.to_string()));
+            }
+
+            let u = &args[0];
+            let du = differentiate(u, variable, mode)?;
+            differentiate_function(name, u, du, mode)?
+        }
+        BinaryOperation(ref u, op, ref v) => {
+            let du = differentiate(u, variable, mode)?;
+            let dv = differentiate(v, variable, mode)?;
+
+            match op {
+                BinaryOperator::Plus => add(du, dv),
+                BinaryOperator::Minus => sub(du, dv),
+                BinaryOperator::Multiplication => {
+                    add(mul(du, (**v).clone()), mul((**u).clone(), dv))
+                }
+                BinaryOperator::Divide => div(
+                    sub(mul(du, (**v).clone()), mul((**u).clone(), dv)),
+                    pow((**v).clone(), Number(2f64)),
+                ),
+                BinaryOperator::Power => {
+                    let (u, v) = ((**u).clone(), (**v).clone());
+
+                    if !contains_variable(&v, variable) {
+                        mul(mul(v.clone(), pow(u, sub(v, Number(1f64)))), du)
+                    } else if !contains_variable(&u, variable) {
+                        mul(mul(pow(u.clone(), v), call("ln", u)), dv)
+                    } else {
+                        mul(
+                            pow(u.clone(), v.clone()),
+                            add(mul(dv, call("ln", u.clone())), div(mul(v, du), u)),
+                        )
+                    }
+                }
+            }
+        }
+        Expression::UnaryPrefixOperation(op, ref exp) => {
+            let derivative = differentiate(exp, variable, mode)?;
+
+            match op {
+                PrefixOperator::PlusSign => derivative,
+                PrefixOperator::MinusSign => neg(derivative),
+            }
+        }
+        Expression::UnarySuffixOperation(ref exp, op) => match op {
+            // Angle units only scale their operand
+            SuffixOperator::Degree | SuffixOperator::Radian | SuffixOperator::Gradian => {
+                Expression::UnarySuffixOperation(Box::new(differentiate(exp, variable, mode)?), op)
+            }
+            SuffixOperator::Factorial => {
+                return Err(ExecError::NotDifferentiable(expression.to_string()))
+            }
+        },
+        Expression::Equation(..) => return Err(ExecError::UnexpectedEquation),
+        Expression::Error => return Err(ExecError::Unparsed),
+    };
+
+    Ok(derivative)
+}
+
+fn differentiate_function(
+    name: &str,
+    u: &Expression,
+    du: Expression,
+    mode: AngleMode,
+) -> Result<Expression, ExecError> {
+    let u = u.clone();
+
+    let derivative = match name {
+        "sin" => in_radians(mul(call("cos", u), du), mode),
+        "cos" => in_radians(neg(mul(call("sin", u), du)), mode),
+        "tan" => in_radians(div(du, pow(call("cos", u), Number(2f64))), mode),
+        "asin" => in_mode(div(du, call("sqrt", sub(Number(1f64), square(u)))), mode),
+        "acos" => in_mode(
+            neg(div(du, call("sqrt", sub(Number(1f64), square(u))))),
+            mode,
+        ),
+        "atan" => in_mode(div(du, add(Number(1f64), square(u))), mode),
+        "sinh" => mul(call("cosh", u), du),
+        "cosh" => mul(call("sinh", u), du),
+        "tanh" => div(du, pow(call("cosh", u), Number(2f64))),
+        "asinh" => div(du, call("sqrt", add(square(u), Number(1f64)))),
+        "acosh" => div(du, call("sqrt", sub(square(u), Number(1f64)))),
+        "atanh" => div(du, sub(Number(1f64), square(u))),
+        "sqrt" => div(du, mul(Number(2f64), call("sqrt", u))),
+        "ln" => div(du, u),
+        _ => return Err(ExecError::UnknownFunction(name.to_string())),
+    };
+
+    Ok(derivative)
+}
+
+/// Scales the derivative of a function that takes an angle in `mode`
+fn in_radians(derivative: Expression, mode: AngleMode) -> Expression {
+    match mode {
+        AngleMode::Radian => derivative,
+        AngleMode::Degree => div(mul(derivative, pi()), Number(180f64)),
+        AngleMode::Gradian => div(mul(derivative, pi()), Number(200f64)),
+    }
+}
+
+/// Scales the derivative of a function that returns an angle in `mode`
+fn in_mode(derivative: Expression, mode: AngleMode) -> Expression {
+    match mode {
+        AngleMode::Radian => derivative,
+        AngleMode::Degree => div(mul(derivative, Number(180f64)), pi()),
+        AngleMode::Gradian => div(mul(derivative, Number(200f64)), pi()),
+    }
+}
+
+fn add(left: Expression, right: Expression) -> Expression {
+    BinaryOperation(Box::new(left), BinaryOperator::Plus, Box::new(right))
+}
+
+fn sub(left: Expression, right: Expression) -> Expression {
+    BinaryOperation(Box::new(left), BinaryOperator::Minus, Box::new(right))
+}
+
+/// A term with a vanishing derivative as factor doesn't appear in the
+/// derivative, the simplifier can't drop it since `0 * x` isn't `0` for all x
+fn mul(left: Expression, right: Expression) -> Expression {
+    if left == Number(0f64) || right == Number(0f64) {
+        return Number(0f64);
+    }
+
+    BinaryOperation(
+        Box::new(left),
+        BinaryOperator::Multiplication,
+        Box::new(right),
+    )
+}
+
+fn div(left: Expression, right: Expression) -> Expression {
+    BinaryOperation(Box::new(left), BinaryOperator::Divide, Box::new(right))
+}
+
+fn pow(left: Expression, right: Expression) -> Expression {
+    BinaryOperation(Box::new(left), BinaryOperator::Power, Box::new(right))
+}
+
+fn square(exp: Expression) -> Expression {
+    pow(exp, Number(2f64))
+}
+
+fn neg(exp: Expression) -> Expression {
+    Expression::UnaryPrefixOperation(PrefixOperator::MinusSign, Box::new(exp))
+}
+
+fn call(name: &str, arg: Expression) -> Expression {
+    FunctionCall(name.to_string(), vec![arg])
+}
+
+fn pi() -> Expression {
+    Identifier(String::from("pi"))
+}
+
+#[cfg(test)]
+fn parse_and_differentiate(input: &str, mode: AngleMode) -> String {
+    use parser::ast::Root;
+    use parser::parse;
+
+    let context = Context {
+        angle_mode: mode,
+        ..Context::new()
+    };
+
+    match parse(input) {
+        Ok(Root::Expression(exp)) => {
+            match symbolic_derivative(&[exp, Identifier(String::from("x"))], &context) {
+                Ok(derivative) => derivative.to_string(),
+                Err(err) => err.to_string(),
+            }
+        }
+        Err(err) => panic!("failed to parse {}: {:?}", input, err),
+    }
+}
+
+#[test]
+fn test_differentiate_polynomial() {
+    assert_eq!(
+        parse_and_differentiate("x^3 + 2x", AngleMode::Radian),
+        "3x^2 + 2"
+    );
+    assert_eq!(parse_and_differentiate("5", AngleMode::Radian), "0");
+    assert_eq!(parse_and_differentiate("y * x", AngleMode::Radian), "y");
+}
+
+#[test]
+fn test_differentiate_quotient() {
+    assert_eq!(
+        parse_and_differentiate("1 / x", AngleMode::Radian),
+        "-1 / x^2"
+    );
+}
+
+#[test]
+fn test_differentiate_chain_rule() {
+    assert_eq!(
+        parse_and_differentiate("sin(2x)", AngleMode::Radian),
+        "2cos(2x)"
+    );
+    assert_eq!(parse_and_differentiate("ln(x)", AngleMode::Radian), "1 / x");
+}
+
+#[test]
+fn test_differentiate_exponential() {
+    assert_eq!(
+        parse_and_differentiate("2^x", AngleMode::Radian),
+        "2^x * ln(2)"
+    );
+}
+
+#[test]
+fn test_differentiate_respects_angle_mode() {
+    assert_eq!(
+        parse_and_differentiate("sin(x)", AngleMode::Degree),
+        "cos(x) * pi / 180"
+    );
+    assert_eq!(
+        parse_and_differentiate("sin(x rad)", AngleMode::Degree),
+        "cos(x rad) * 1 rad * pi / 180"
+    );
+}
+
+#[test]
+fn test_differentiate_factorial() {
+    assert_eq!(
+        parse_and_differentiate("x!", AngleMode::Radian),
+        "x! is not differentiable"
+    );
+}
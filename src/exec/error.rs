@@ -0,0 +1,82 @@
+use std::error::Error;
+use std::fmt;
+
+/// An error that occurs while evaluating a syntactically valid expression
+#[derive(Debug, PartialEq, Clone)]
+pub enum ExecError {
+    UnknownIdentifier(String),
+    UnknownFunction(String),
+    WrongArgumentCount {
+        function: String,
+        expected: usize,
+        found: usize,
+    },
+    /// The expression has no value, e.g. `tan(90deg)`
+    Undefined(String),
+    /// The result can't be represented as a number, e.g. `200!`
+    TooLarge(String),
+    /// The function needs an identifier as argument, e.g. `diff(x^2, x)`
+    ExpectedVariable(String),
+    NotDifferentiable(String),
+    /// The function returns an expression instead of a number, so it can't
+    /// be used as part of a calculation
+    Symbolic(String),
+    /// An equation outside of `solve`
+    UnexpectedEquation,
+    NoRealSolution,
+    InfinitelyManySolutions,
+    /// A function with more than one result is used as part of a calculation
+    MultipleSolutions(String, usize),
+    /// A numerical method didn't reach the required accuracy, e.g. for the
+    /// integral of a function with a pole
+    NotConverged(String),
+    /// A plot is used as part of a calculation
+    UnexpectedPlot,
+    /// The expression contains parts that couldn't be parsed
+    Unparsed,
+}
+
+impl fmt::Display for ExecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ExecError::UnknownIdentifier(ref name) => write!(f, "unknown identifier {}", name),
+            ExecError::UnknownFunction(ref name) => write!(f, "unknown function {}", name),
+            ExecError::WrongArgumentCount {
+                ref function,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} expects {} argument{} but got {}",
+                function,
+                expected,
+                if expected == 1 { "" } else { "s" },
+                found
+            ),
+            ExecError::Undefined(ref what) => write!(f, "{} is undefined", what),
+            ExecError::TooLarge(ref what) => write!(f, "{} is too large", what),
+            ExecError::ExpectedVariable(ref function) => {
+                write!(f, "{} expects a variable", function)
+            }
+            ExecError::NotDifferentiable(ref what) => write!(f, "{} is not differentiable", what),
+            ExecError::Symbolic(ref function) => write!(
+                f,
+                "{} returns an expression and can't be used in a calculation",
+                function
+            ),
+            ExecError::UnexpectedEquation => write!(f, "equations can only be used in solve"),
+            ExecError::NoRealSolution => write!(f, "no real solution"),
+            ExecError::InfinitelyManySolutions => write!(f, "infinitely many solutions"),
+            ExecError::MultipleSolutions(ref function, count) => write!(
+                f,
+                "{} has {} solutions and can't be used in a calculation",
+                function, count
+            ),
+            ExecError::NotConverged(ref function) => write!(f, "{} did not converge", function),
+            ExecError::UnexpectedPlot => write!(f, "plots can't be used in a calculation"),
+            ExecError::Unparsed => write!(f, "the expression contains errors"),
+        }
+    }
+}
+
+impl Error for ExecError {}
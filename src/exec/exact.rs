@@ -0,0 +1,162 @@
+use std::fmt;
+
+use super::context::{AngleMode, Context};
+use super::expression::execute_expression;
+use parser::ast::{Expression, PrefixOperator};
+
+/// A number of the form `numerator / denominator * sqrt(radicand)`
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Exact {
+    pub numerator: i64,
+    pub denominator: i64,
+    pub radicand: i64,
+}
+
+impl Exact {
+    fn new(numerator: i64, denominator: i64, radicand: i64) -> Exact {
+        Exact {
+            numerator,
+            denominator,
+            radicand,
+        }
+    }
+
+    fn integer(n: i64) -> Exact {
+        Exact::new(n, 1, 1)
+    }
+
+    fn negate(self) -> Exact {
+        Exact::new(-self.numerator, self.denominator, self.radicand)
+    }
+
+    /// The nearest floating point number
+    pub fn to_f64(self) -> f64 {
+        self.numerator as f64 * (self.radicand as f64).sqrt() / self.denominator as f64
+    }
+}
+
+impl fmt::Display for Exact {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.numerator < 0 {
+            write!(f, "-")?;
+        }
+
+        let numerator = self.numerator.abs();
+
+        if self.radicand == 1 {
+            write!(f, "{}", numerator)?;
+        } else if numerator == 1 {
+            write!(f, "sqrt({})", self.radicand)?;
+        } else {
+            write!(f, "{}sqrt({})", numerator, self.radicand)?;
+        }
+
+        if self.denominator != 1 {
+            write!(f, "/{}", self.denominator)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the exact value of an expression if it is (a negation of) a
+/// trigonometric function at an angle for which the value is well known,
+/// e.g. `sin(30deg) = 1/2`
+pub fn exact_value(expression: &Expression, context: &Context) -> Option<Exact> {
+    match *expression {
+        Expression::UnaryPrefixOperation(PrefixOperator::PlusSign, ref exp) => {
+            exact_value(exp, context)
+        }
+        Expression::UnaryPrefixOperation(PrefixOperator::MinusSign, ref exp) => {
+            exact_value(exp, context).map(Exact::negate)
+        }
+        Expression::FunctionCall(ref name, ref args) if args.len() == 1 => {
+            let exact_function: fn(i64) -> Option<Exact> = match name.as_str() {
+                "sin" => exact_sin,
+                "cos" => |degrees| exact_sin((degrees + 90) % 360),
+                "tan" => exact_tan,
+                _ => return None,
+            };
+
+            let angle = execute_expression(&args[0], context).ok()?;
+            let degrees = context.angle_mode.convert(angle, AngleMode::Degree);
+
+            if (degrees - degrees.round()).abs() > 1e-9 {
+                return None;
+            }
+
+            exact_function((degrees.round() as i64).rem_euclid(360))
+        }
+        _ => None,
+    }
+}
+
+/// Takes an angle in whole degrees in the range `0..360`
+fn exact_sin(degrees: i64) -> Option<Exact> {
+    let reference = match degrees % 180 {
+        r if r > 90 => 180 - r,
+        r => r,
+    };
+
+    let value = match reference {
+        0 => Exact::integer(0),
+        30 => Exact::new(1, 2, 1),
+        45 => Exact::new(1, 2, 2),
+        60 => Exact::new(1, 2, 3),
+        90 => Exact::integer(1),
+        _ => return None,
+    };
+
+    if degrees > 180 {
+        Some(value.negate())
+    } else {
+        Some(value)
+    }
+}
+
+/// Takes an angle in whole degrees in the range `0..360`
+fn exact_tan(degrees: i64) -> Option<Exact> {
+    let value = match degrees % 180 {
+        0 => Exact::integer(0),
+        30 => Exact::new(1, 3, 3),
+        45 => Exact::integer(1),
+        60 => Exact::new(1, 1, 3),
+        120 => Exact::new(-1, 1, 3),
+        135 => Exact::integer(-1),
+        150 => Exact::new(-1, 3, 3),
+        _ => return None,
+    };
+
+    Some(value)
+}
+
+#[test]
+fn test_exact_sin() {
+    assert_eq!(exact_sin(30), Some(Exact::new(1, 2, 1)));
+    assert_eq!(exact_sin(135), Some(Exact::new(1, 2, 2)));
+    assert_eq!(exact_sin(240), Some(Exact::new(-1, 2, 3)));
+    assert_eq!(exact_sin(270), Some(Exact::integer(-1)));
+    assert_eq!(exact_sin(10), None);
+}
+
+#[test]
+fn test_exact_tan() {
+    assert_eq!(exact_tan(45), Some(Exact::integer(1)));
+    assert_eq!(exact_tan(210), Some(Exact::new(1, 3, 3)));
+    assert_eq!(exact_tan(90), None);
+}
+
+#[test]
+fn test_exact_display() {
+    assert_eq!(Exact::new(1, 2, 1).to_string(), "1/2");
+    assert_eq!(Exact::new(-1, 2, 3).to_string(), "-sqrt(3)/2");
+    assert_eq!(Exact::new(1, 1, 3).to_string(), "sqrt(3)");
+    assert_eq!(Exact::new(2, 3, 2).to_string(), "2sqrt(2)/3");
+    assert_eq!(Exact::integer(-1).to_string(), "-1");
+}
+
+#[test]
+fn test_exact_to_f64() {
+    assert_eq!(Exact::new(1, 2, 1).to_f64(), 0.5);
+    assert_eq!(Exact::new(-1, 2, 3).to_f64(), -(3f64.sqrt()) / 2f64);
+}
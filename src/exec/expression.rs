@@ -1,13 +1,63 @@
+use std::f64::consts::{E, PI};
+
+use super::context::{AngleMode, Context};
+use super::derivative::evaluate_derivative;
+use super::error::ExecError;
+use super::functions::call_function;
+use super::integrate::integrate;
+use super::solve::solve;
+use super::value::num_as_string;
 use parser::ast::{BinaryOperator, Expression, PrefixOperator, SuffixOperator};
 
-// TODO: add test
 // TODO: handle semantic errors e.g. 1 / 0
-pub fn execute_expression(expression: Expression) -> f64 {
-    match expression {
+pub fn execute_expression(expression: &Expression, context: &Context) -> Result<f64, ExecError> {
+    let value = match *expression {
         Expression::Number(n) => n,
-        Expression::BinaryOperation(left, op, right) => {
-            let left = execute_expression(*left);
-            let right = execute_expression(*right);
+        Expression::Identifier(ref name) => match context.variables.get(name) {
+            Some(&value) => value,
+            None => match constant(name) {
+                Some(value) => value,
+                None => return Err(ExecError::UnknownIdentifier(name.clone())),
+            },
+        },
+        Expression::FunctionCall(ref name, ref args) if name == "diff" => {
+            evaluate_derivative(args, context)?
+        }
+        // Simplifying doesn't change the value of an expression
+        Expression::FunctionCall(ref name, ref args) if name == "simplify" => {
+            if args.len() != 1 {
+                return Err(ExecError::WrongArgumentCount {
+                    function: name.clone(),
+                    expected: 1,
+                    found: args.len(),
+                });
+            }
+            execute_expression(&args[0], context)?
+        }
+        Expression::FunctionCall(ref name, ref args) if name == "solve" => {
+            let (_, solutions) = solve(args, context)?;
+            if solutions.len() != 1 {
+                return Err(ExecError::MultipleSolutions(name.clone(), solutions.len()));
+            }
+            solutions[0]
+        }
+        Expression::FunctionCall(ref name, ref args) if name == "integrate" => {
+            integrate(args, context)?.0
+        }
+        Expression::FunctionCall(ref name, _) if name == "plot" => {
+            return Err(ExecError::UnexpectedPlot)
+        }
+        Expression::FunctionCall(ref name, ref args) => {
+            let args = args
+                .iter()
+                .map(|arg| execute_expression(arg, context))
+                .collect::<Result<Vec<f64>, ExecError>>()?;
+
+            call_function(name, &args, context)?
+        }
+        Expression::BinaryOperation(ref left, op, ref right) => {
+            let left = execute_expression(left, context)?;
+            let right = execute_expression(right, context)?;
 
             match op {
                 BinaryOperator::Plus => left + right,
@@ -17,58 +67,237 @@ pub fn execute_expression(expression: Expression) -> f64 {
                 BinaryOperator::Power => left.powf(right),
             }
         }
-        Expression::UnaryPrefixOperation(op, right) => {
-            let right = execute_expression(*right);
+        Expression::UnaryPrefixOperation(op, ref right) => {
+            let right = execute_expression(right, context)?;
 
             match op {
                 PrefixOperator::PlusSign => right,
                 PrefixOperator::MinusSign => -right,
             }
         }
-        Expression::UnarySuffixOperation(left, op) => {
-            let left = execute_expression(*left);
+        Expression::UnarySuffixOperation(ref left, op) => {
+            let left = execute_expression(left, context)?;
 
             match op {
-                SuffixOperator::Factorial => factorial(left as u64) as f64,
+                SuffixOperator::Factorial => factorial(left)?,
+                SuffixOperator::Degree => AngleMode::Degree.convert(left, context.angle_mode),
+                SuffixOperator::Radian => AngleMode::Radian.convert(left, context.angle_mode),
+                SuffixOperator::Gradian => AngleMode::Gradian.convert(left, context.angle_mode),
             }
         }
+        Expression::Equation(..) => return Err(ExecError::UnexpectedEquation),
+        Expression::Error => return Err(ExecError::Unparsed),
+    };
+
+    Ok(value)
+}
+
+pub fn contains_variable(expression: &Expression, variable: &str) -> bool {
+    match *expression {
+        Expression::Number(_) | Expression::Error => false,
+        Expression::Identifier(ref name) => name == variable,
+        Expression::FunctionCall(_, ref args) => {
+            args.iter().any(|arg| contains_variable(arg, variable))
+        }
+        Expression::BinaryOperation(ref left, _, ref right)
+        | Expression::Equation(ref left, ref right) => {
+            contains_variable(left, variable) || contains_variable(right, variable)
+        }
+        Expression::UnaryPrefixOperation(_, ref exp)
+        | Expression::UnarySuffixOperation(ref exp, _) => contains_variable(exp, variable),
+    }
+}
+
+/// Whether the value of the expression is an angle in the angle mode, i.e. it
+/// stems from an angle literal or an inverse trigonometric function
+pub fn is_angle(expression: &Expression) -> bool {
+    match *expression {
+        Expression::UnarySuffixOperation(_, op) => op != SuffixOperator::Factorial,
+        Expression::FunctionCall(ref name, _) => ["asin", "acos", "atan"].contains(&name.as_str()),
+        Expression::UnaryPrefixOperation(_, ref exp) => is_angle(exp),
+        // Plain numbers are taken to be in the angle mode, e.g. `asin(1) - 10`
+        Expression::BinaryOperation(ref left, BinaryOperator::Plus, ref right)
+        | Expression::BinaryOperation(ref left, BinaryOperator::Minus, ref right) => {
+            is_angle(left) || is_angle(right)
+        }
+        // The product or ratio of two angles isn't an angle
+        Expression::BinaryOperation(ref left, BinaryOperator::Multiplication, ref right) => {
+            is_angle(left) != is_angle(right)
+        }
+        Expression::BinaryOperation(ref left, BinaryOperator::Divide, ref right) => {
+            is_angle(left) && !is_angle(right)
+        }
+        _ => false,
+    }
+}
+
+/// Whether a part of the expression couldn't be parsed
+pub fn contains_error(expression: &Expression) -> bool {
+    match *expression {
+        Expression::Error => true,
+        Expression::Number(_) | Expression::Identifier(_) => false,
+        Expression::FunctionCall(_, ref args) => args.iter().any(contains_error),
+        Expression::BinaryOperation(ref left, _, ref right)
+        | Expression::Equation(ref left, ref right) => {
+            contains_error(left) || contains_error(right)
+        }
+        Expression::UnaryPrefixOperation(_, ref exp)
+        | Expression::UnarySuffixOperation(ref exp, _) => contains_error(exp),
+    }
+}
+
+/// Functions that can take a noticeable time to evaluate
+static EXPENSIVE_FUNCTIONS: [&str; 2] = ["integrate", "solve"];
+
+pub fn calls_expensive_function(expression: &Expression) -> bool {
+    match *expression {
+        Expression::Number(_) | Expression::Identifier(_) | Expression::Error => false,
+        Expression::FunctionCall(ref name, ref args) => {
+            EXPENSIVE_FUNCTIONS.contains(&name.as_str())
+                || args.iter().any(calls_expensive_function)
+        }
+        Expression::BinaryOperation(ref left, _, ref right)
+        | Expression::Equation(ref left, ref right) => {
+            calls_expensive_function(left) || calls_expensive_function(right)
+        }
+        Expression::UnaryPrefixOperation(_, ref operand)
+        | Expression::UnarySuffixOperation(ref operand, _) => calls_expensive_function(operand),
     }
 }
 
-fn factorial(value: u64) -> u64 {
-    let mut curr = 1;
-    for i in 1..=value {
+/// The names of the built-in constants, without their symbols
+pub static CONSTANT_NAMES: [&str; 3] = ["pi", "e", "inf"];
+
+pub fn constant(name: &str) -> Option<f64> {
+    match name {
+        "pi" | "π" => Some(PI),
+        "e" => Some(E),
+        "inf" | "∞" => Some(f64::INFINITY),
+        _ => None,
+    }
+}
+
+/// Only defined for natural numbers
+fn factorial(value: f64) -> Result<f64, ExecError> {
+    if value < 0f64 || value.fract() != 0f64 {
+        let operand = num_as_string(value);
+        return Err(ExecError::Undefined(if value < 0f64 {
+            format!("({})!", operand)
+        } else {
+            format!("{}!", operand)
+        }));
+    }
+
+    let mut curr = 1f64;
+    let mut i = 2f64;
+    while i <= value {
         curr *= i;
+        i += 1f64;
+
+        if curr.is_infinite() {
+            return Err(ExecError::TooLarge(format!("{}!", num_as_string(value))));
+        }
     }
-    curr
+    Ok(curr)
 }
 
 #[test]
 fn test_factorial_of_0() {
-    assert_eq!(factorial(0u64), 1u64);
+    assert_eq!(factorial(0f64), Ok(1f64));
 }
 
 #[test]
 fn test_factorial_of_1() {
-    assert_eq!(factorial(1u64), 1u64);
+    assert_eq!(factorial(1f64), Ok(1f64));
 }
 
 #[test]
 fn test_factorial_of_2() {
-    assert_eq!(factorial(2u64), 2u64);
+    assert_eq!(factorial(2f64), Ok(2f64));
 }
 
 #[test]
 fn test_factorial_of_3() {
-    assert_eq!(factorial(3u64), 6u64);
+    assert_eq!(factorial(3f64), Ok(6f64));
 }
 
 #[test]
 fn test_factorial_of_4() {
-    assert_eq!(factorial(4u64), 24u64);
+    assert_eq!(factorial(4f64), Ok(24f64));
 }
 
 #[test]
 fn test_factorial_of_5() {
-    assert_eq!(factorial(5u64), 120u64);
+    assert_eq!(factorial(5f64), Ok(120f64));
+}
+
+#[test]
+fn test_factorial_of_non_natural_numbers() {
+    assert_eq!(
+        factorial(1.5),
+        Err(ExecError::Undefined(String::from("1.5!")))
+    );
+    assert_eq!(
+        factorial(-3f64),
+        Err(ExecError::Undefined(String::from("(-3)!")))
+    );
+    assert!(factorial(f64::NAN).is_err());
+}
+
+#[test]
+fn test_factorial_too_large() {
+    assert_eq!(factorial(170f64).map(f64::is_finite), Ok(true));
+    assert_eq!(
+        factorial(171f64),
+        Err(ExecError::TooLarge(String::from("171!")))
+    );
+    assert!(factorial(1e300).is_err());
+}
+
+#[cfg(test)]
+fn execute_with_mode(expression: Expression, angle_mode: AngleMode) -> Result<f64, ExecError> {
+    execute_expression(
+        &expression,
+        &Context {
+            angle_mode,
+            ..Context::new()
+        },
+    )
+}
+
+#[test]
+fn test_constants() {
+    let pi = Expression::Identifier(String::from("pi"));
+    assert_eq!(execute_with_mode(pi, AngleMode::Radian), Ok(PI));
+}
+
+#[test]
+fn test_variables_shadow_constants() {
+    let e = Expression::Identifier(String::from("e"));
+    let context = Context::new().with_variable("e", 42f64);
+    assert_eq!(execute_expression(&e, &context), Ok(42f64));
+}
+
+#[test]
+fn test_unknown_identifier() {
+    let foo = Expression::Identifier(String::from("foo"));
+    assert_eq!(
+        execute_with_mode(foo, AngleMode::Radian),
+        Err(ExecError::UnknownIdentifier(String::from("foo")))
+    );
+}
+
+#[test]
+fn test_angle_literal_overrides_mode() {
+    let angle = Expression::UnarySuffixOperation(
+        Box::new(Expression::Identifier(String::from("pi"))),
+        SuffixOperator::Radian,
+    );
+    assert_eq!(execute_with_mode(angle, AngleMode::Degree), Ok(180f64));
+}
+
+#[test]
+fn test_trigonometric_function_uses_mode() {
+    let sin = Expression::FunctionCall(String::from("sin"), vec![Expression::Number(90f64)]);
+    assert_eq!(execute_with_mode(sin, AngleMode::Degree), Ok(1f64));
 }
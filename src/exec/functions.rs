@@ -0,0 +1,138 @@
+use super::context::{AngleMode, Context};
+use super::error::ExecError;
+
+/// The names of the built-in functions, including the ones handled
+/// elsewhere like `solve`
+pub static FUNCTION_NAMES: [&str; 19] = [
+    "sin",
+    "cos",
+    "tan",
+    "asin",
+    "acos",
+    "atan",
+    "sinh",
+    "cosh",
+    "tanh",
+    "asinh",
+    "acosh",
+    "atanh",
+    "sqrt",
+    "ln",
+    "diff",
+    "simplify",
+    "solve",
+    "integrate",
+    "plot",
+];
+
+/// Calls the built-in function `name` with already evaluated arguments
+pub fn call_function(name: &str, args: &[f64], context: &Context) -> Result<f64, ExecError> {
+    let mode = context.angle_mode;
+
+    let function: fn(f64, AngleMode) -> f64 = match name {
+        "sin" => |x, mode| mode.to_radians(x).sin(),
+        "cos" => |x, mode| mode.to_radians(x).cos(),
+        "tan" => |x, mode| mode.to_radians(x).tan(),
+        "asin" => |x, mode| AngleMode::Radian.convert(x.asin(), mode),
+        "acos" => |x, mode| AngleMode::Radian.convert(x.acos(), mode),
+        "atan" => |x, mode| AngleMode::Radian.convert(x.atan(), mode),
+        "sinh" => |x, _| x.sinh(),
+        "cosh" => |x, _| x.cosh(),
+        "tanh" => |x, _| x.tanh(),
+        "asinh" => |x, _| x.asinh(),
+        "acosh" => |x, _| x.acosh(),
+        "atanh" => |x, _| x.atanh(),
+        "sqrt" => |x, _| x.sqrt(),
+        "ln" => |x, _| x.ln(),
+        _ => return Err(ExecError::UnknownFunction(name.to_string())),
+    };
+
+    if args.len() != 1 {
+        return Err(ExecError::WrongArgumentCount {
+            function: name.to_string(),
+            expected: 1,
+            found: args.len(),
+        });
+    }
+
+    let arg = args[0];
+
+    if name == "tan" && is_odd_multiple_of_right_angle(arg, mode) {
+        let degrees = mode.convert(arg, AngleMode::Degree).round();
+        return Err(ExecError::Undefined(format!("tan({}deg)", degrees)));
+    }
+
+    Ok(function(arg, mode))
+}
+
+fn is_odd_multiple_of_right_angle(angle: f64, mode: AngleMode) -> bool {
+    let right_angles = mode.convert(angle, AngleMode::Degree) / 90f64;
+    (right_angles - right_angles.round()).abs() < 1e-9 && right_angles.round() % 2f64 != 0f64
+}
+
+#[test]
+fn test_sin_in_radian_mode() {
+    let context = Context {
+        angle_mode: AngleMode::Radian,
+        ..Context::new()
+    };
+    assert_eq!(call_function("sin", &[0f64], &context), Ok(0f64));
+}
+
+#[test]
+fn test_asin_returns_angle_in_mode() {
+    let context = Context {
+        angle_mode: AngleMode::Degree,
+        ..Context::new()
+    };
+    assert_eq!(call_function("asin", &[1f64], &context), Ok(90f64));
+}
+
+#[test]
+fn test_hyperbolic_functions_ignore_angle_mode() {
+    let context = Context {
+        angle_mode: AngleMode::Degree,
+        ..Context::new()
+    };
+    assert_eq!(call_function("sinh", &[0f64], &context), Ok(0f64));
+    assert_eq!(call_function("cosh", &[0f64], &context), Ok(1f64));
+}
+
+#[test]
+fn test_tan_of_right_angle_is_undefined() {
+    let context = Context {
+        angle_mode: AngleMode::Degree,
+        ..Context::new()
+    };
+    assert!(call_function("tan", &[90f64], &context).is_err());
+    assert!(call_function("tan", &[270f64], &context).is_err());
+    assert!(call_function("tan", &[180f64], &context).is_ok());
+}
+
+#[test]
+fn test_function_names() {
+    // The others are evaluated before they get here
+    for name in FUNCTION_NAMES.iter().take(14) {
+        assert!(call_function(name, &[0.5], &Context::new()).is_ok());
+    }
+}
+
+#[test]
+fn test_unknown_function() {
+    assert_eq!(
+        call_function("foo", &[1f64], &Context::new()),
+        Err(ExecError::UnknownFunction(String::from("foo")))
+    );
+}
+
+#[test]
+fn test_wrong_argument_count() {
+    assert_eq!(
+        call_function("sin", &[1f64, 2f64], &Context::new()),
+        Err(ExecError::WrongArgumentCount {
+            function: String::from("sin"),
+            expected: 1,
+            found: 2,
+        })
+    );
+}
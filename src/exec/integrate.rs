@@ -0,0 +1,264 @@
+use super::context::Context;
+use super::error::ExecError;
+use super::expression::execute_expression;
+use parser::ast::Expression;
+
+/// Nodes of the 15 point Kronrod rule on `[-1, 1]`, the odd ones are also
+/// the nodes of the embedded 7 point Gauss rule
+const KRONROD_NODES: [f64; 8] = [
+    0.991_455_371_120_812_6,
+    0.949_107_912_342_758_5,
+    0.864_864_423_359_769_1,
+    0.741_531_185_599_394_4,
+    0.586_087_235_467_691_1,
+    0.405_845_151_377_397_2,
+    0.207_784_955_007_898_5,
+    0f64,
+];
+const KRONROD_WEIGHTS: [f64; 8] = [
+    0.022_935_322_010_529_22,
+    0.063_092_092_629_978_55,
+    0.104_790_010_322_250_2,
+    0.140_653_259_715_525_9,
+    0.169_004_726_639_267_9,
+    0.190_350_578_064_785_4,
+    0.204_432_940_075_298_9,
+    0.209_482_141_084_727_8,
+];
+const GAUSS_WEIGHTS: [f64; 4] = [
+    0.129_484_966_168_869_7,
+    0.279_705_391_489_276_7,
+    0.381_830_050_505_118_9,
+    0.417_959_183_673_469_4,
+];
+
+const ABSOLUTE_TOLERANCE: f64 = 1e-12;
+const RELATIVE_TOLERANCE: f64 = 1e-10;
+/// Number of subintervals after which the integral is considered divergent
+const MAX_SEGMENTS: usize = 2000;
+
+/// A part of the integration range with the estimated integral over it
+struct Segment {
+    lower: f64,
+    upper: f64,
+    value: f64,
+    error: f64,
+}
+
+/// Handles `integrate(expression, x, a, b)`, returns the value of the
+/// integral and an estimate of its absolute error. The bounds may be
+/// infinite, e.g. `integrate(e^(-x^2), x, -inf, inf)`.
+pub fn integrate(args: &[Expression], context: &Context) -> Result<(f64, f64), ExecError> {
+    if args.len() != 4 {
+        return Err(ExecError::WrongArgumentCount {
+            function: String::from("integrate"),
+            expected: 4,
+            found: args.len(),
+        });
+    }
+
+    let variable = match args[1] {
+        Expression::Identifier(ref name) => name,
+        _ => return Err(ExecError::ExpectedVariable(String::from("integrate"))),
+    };
+
+    let a = execute_expression(&args[2], context)?;
+    let b = execute_expression(&args[3], context)?;
+
+    if a.is_nan() || b.is_nan() {
+        return Err(ExecError::Undefined(String::from("the integration range")));
+    }
+    if a == b {
+        return Ok((0f64, 0f64));
+    }
+    if a > b {
+        return integrate_range(&args[0], variable, context, b, a)
+            .map(|(value, error)| (-value, error));
+    }
+
+    integrate_range(&args[0], variable, context, a, b)
+}
+
+/// Integrates over `[a, b]` with `a < b`. Infinite bounds are mapped onto a
+/// finite range, e.g. `x = a + t / (1 - t)` maps `[0, 1)` onto `[a, inf)`.
+fn integrate_range(
+    integrand: &Expression,
+    variable: &str,
+    context: &Context,
+    a: f64,
+    b: f64,
+) -> Result<(f64, f64), ExecError> {
+    let mut scope = context.with_variable(variable, 0f64);
+
+    let mut evaluate = |x: f64| -> Result<f64, ExecError> {
+        if let Some(value) = scope.variables.get_mut(variable) {
+            *value = x;
+        }
+        execute_expression(integrand, &scope)
+    };
+
+    match (a.is_finite(), b.is_finite()) {
+        (true, true) => adaptive_quadrature(&mut evaluate, a, b),
+        (true, false) => adaptive_quadrature(
+            &mut |t| Ok(evaluate(a + t / (1f64 - t))? / ((1f64 - t) * (1f64 - t))),
+            0f64,
+            1f64,
+        ),
+        (false, true) => adaptive_quadrature(
+            &mut |t| Ok(evaluate(b - (1f64 - t) / t)? / (t * t)),
+            0f64,
+            1f64,
+        ),
+        (false, false) => adaptive_quadrature(
+            &mut |t| {
+                let s = 1f64 - t * t;
+                Ok(evaluate(t / s)? * (1f64 + t * t) / (s * s))
+            },
+            -1f64,
+            1f64,
+        ),
+    }
+}
+
+/// Repeatedly bisects the segment with the largest error estimate until the
+/// total error is within the tolerance
+fn adaptive_quadrature<F>(function: &mut F, a: f64, b: f64) -> Result<(f64, f64), ExecError>
+where
+    F: FnMut(f64) -> Result<f64, ExecError>,
+{
+    let mut segments = vec![gauss_kronrod(function, a, b)?];
+
+    loop {
+        let value: f64 = segments.iter().map(|segment| segment.value).sum();
+        let error: f64 = segments.iter().map(|segment| segment.error).sum();
+
+        if !value.is_finite() || !error.is_finite() || segments.len() >= MAX_SEGMENTS {
+            return Err(ExecError::NotConverged(String::from("integrate")));
+        }
+        if error <= ABSOLUTE_TOLERANCE.max(RELATIVE_TOLERANCE * value.abs()) {
+            return Ok((value, error));
+        }
+
+        let worst = segments
+            .iter()
+            .enumerate()
+            .max_by(|(_, l), (_, r)| l.error.partial_cmp(&r.error).unwrap())
+            .map(|(i, _)| i)
+            .unwrap();
+        let segment = segments.swap_remove(worst);
+        let middle = (segment.lower + segment.upper) / 2f64;
+
+        segments.push(gauss_kronrod(function, segment.lower, middle)?);
+        segments.push(gauss_kronrod(function, middle, segment.upper)?);
+    }
+}
+
+/// Applies the 15 point Gauss-Kronrod rule to `[a, b]`, the difference to the
+/// embedded Gauss rule serves as error estimate
+fn gauss_kronrod<F>(function: &mut F, a: f64, b: f64) -> Result<Segment, ExecError>
+where
+    F: FnMut(f64) -> Result<f64, ExecError>,
+{
+    let center = (a + b) / 2f64;
+    let half_width = (b - a) / 2f64;
+
+    let center_value = function(center)?;
+    let mut kronrod = KRONROD_WEIGHTS[7] * center_value;
+    let mut gauss = GAUSS_WEIGHTS[3] * center_value;
+
+    for i in 0..7 {
+        let offset = half_width * KRONROD_NODES[i];
+        let sum = function(center - offset)? + function(center + offset)?;
+
+        kronrod += KRONROD_WEIGHTS[i] * sum;
+        if i % 2 == 1 {
+            gauss += GAUSS_WEIGHTS[i / 2] * sum;
+        }
+    }
+
+    Ok(Segment {
+        lower: a,
+        upper: b,
+        value: kronrod * half_width,
+        error: ((kronrod - gauss) * half_width).abs(),
+    })
+}
+
+#[cfg(test)]
+fn integrate_input(input: &str) -> Result<(f64, f64), ExecError> {
+    use parser::ast::Root;
+    use parser::parse;
+
+    let context = Context {
+        angle_mode: super::context::AngleMode::Radian,
+        ..Context::new()
+    };
+
+    match parse(input) {
+        Ok(Root::Expression(Expression::FunctionCall(_, args))) => integrate(&args, &context),
+        other => panic!("expected a function call: {:?}", other),
+    }
+}
+
+#[cfg(test)]
+fn assert_integral(input: &str, expected: f64) {
+    let (value, error) = integrate_input(input).unwrap();
+    assert!((value - expected).abs() < 1e-9, "{} = {}", input, value);
+    assert!(error < 1e-9, "{} has error {}", input, error);
+}
+
+#[test]
+fn test_integrate_polynomial() {
+    assert_integral("integrate(x^2, x, 0, 3)", 9f64);
+    assert_integral("integrate(3x^2 + 2x, x, -1, 1)", 2f64);
+}
+
+#[test]
+fn test_integrate_reversed_bounds() {
+    assert_integral("integrate(x, x, 2, 0)", -2f64);
+    assert_eq!(integrate_input("integrate(x, x, 1, 1)"), Ok((0f64, 0f64)));
+}
+
+#[test]
+fn test_integrate_transcendental() {
+    assert_integral("integrate(sin(x), x, 0, pi)", 2f64);
+    assert_integral("integrate(1 / x, x, 1, e)", 1f64);
+    assert_integral("integrate(sqrt(x), x, 0, 1)", 2f64 / 3f64);
+}
+
+#[test]
+fn test_integrate_infinite_bounds() {
+    use std::f64::consts::PI;
+
+    assert_integral("integrate(1 / x^2, x, 1, inf)", 1f64);
+    assert_integral("integrate(e^x, x, -inf, 0)", 1f64);
+    assert_integral("integrate(e^(-x^2), x, -inf, inf)", PI.sqrt());
+}
+
+#[test]
+fn test_integrate_divergent() {
+    assert_eq!(
+        integrate_input("integrate(1 / x, x, 0, 1)"),
+        Err(ExecError::NotConverged(String::from("integrate")))
+    );
+    assert_eq!(
+        integrate_input("integrate(1, x, 0, inf)"),
+        Err(ExecError::NotConverged(String::from("integrate")))
+    );
+}
+
+#[test]
+fn test_integrate_unknown_identifier() {
+    assert_eq!(
+        integrate_input("integrate(x * y, x, 0, 1)"),
+        Err(ExecError::UnknownIdentifier(String::from("y")))
+    );
+}
+
+#[test]
+fn test_integrate_reports_errors_of_integrand() {
+    match integrate_input("integrate(x!, x, 0, 30)") {
+        Err(ExecError::Undefined(ref what)) => assert!(what.ends_with('!'), "{}", what),
+        other => panic!("expected an undefined factorial: {:?}", other),
+    }
+}
@@ -1,19 +1,377 @@
+mod context;
+mod derivative;
+mod error;
+mod exact;
 mod expression;
+mod functions;
+mod integrate;
+mod plot;
+mod simplify;
+mod solve;
+mod value;
 
-use self::expression::execute_expression;
+pub use self::context::{AngleMode, Context};
+pub use self::error::ExecError;
+pub use self::exact::Exact;
+pub use self::expression::CONSTANT_NAMES;
+pub use self::functions::FUNCTION_NAMES;
+pub use self::plot::{value_range, Plot};
+pub use self::simplify::simplify;
+pub use self::value::Value;
+
+use self::derivative::symbolic_derivative;
+use self::exact::exact_value;
+use self::expression::{calls_expensive_function, contains_error, execute_expression, is_angle};
+use self::integrate::integrate;
+use self::plot::plot;
+use self::solve::solve;
 use input_error::InputError;
-use parser::ast::Root;
-use parser::parse;
+use parser::ast::{Expression, Root};
+use parser::{parse, parse_recovering};
 
-pub fn parse_and_execute(input: &str) -> Result<f64, InputError> {
+/// Parses and evaluates the input, errors of both steps are reported as
+/// `InputError`
+pub fn parse_and_execute(input: &str, context: &Context) -> Result<Value, InputError> {
     match parse(input) {
-        Ok(expression) => Ok(execute(expression)),
-        Err(err) => Err(InputError::from_parser_error(err)),
+        Ok(root) => evaluate(&root, context)
+            .map_err(|err| InputError::from_exec_error(&err, input, context)),
+        Err(err) => Err(InputError::from_parser_error(err, input)),
+    }
+}
+
+/// All errors of the input instead of only the first one. It is empty
+/// exactly if `parse_and_execute` succeeds.
+pub fn diagnose(input: &str, context: &Context) -> Vec<InputError> {
+    let (root, errors) = parse_recovering(input);
+
+    if errors.is_empty() {
+        return match evaluate(&root, context) {
+            Ok(_) => Vec::new(),
+            Err(err) => vec![InputError::from_exec_error(&err, input, context)],
+        };
+    }
+
+    errors
+        .into_iter()
+        .map(|err| InputError::from_parser_error(err, input))
+        .collect()
+}
+
+/// Evaluates a parsed input
+pub fn evaluate(input: &Root, context: &Context) -> Result<Value, ExecError> {
+    match *input {
+        Root::Expression(ref expression) => {
+            if let Some(result) = execute_function(expression, context) {
+                return result;
+            }
+
+            match exact_value(expression, context) {
+                Some(exact) => Ok(Value::Exact(exact)),
+                None => execute_expression(expression, context).map(|n| {
+                    if is_angle(expression) {
+                        Value::Angle(n, context.angle_mode)
+                    } else {
+                        Value::Number(n)
+                    }
+                }),
+            }
+        }
     }
 }
 
-fn execute(input: Root) -> f64 {
-    match input {
-        Root::Expression(expression) => execute_expression(expression),
+/// Whether evaluating the input can take a noticeable time, e.g. because it
+/// solves an equation
+pub fn is_expensive(input: &Root) -> bool {
+    match *input {
+        Root::Expression(ref expression) => calls_expensive_function(expression),
     }
 }
+
+#[test]
+fn test_is_expensive() {
+    assert!(!is_expensive(&parse("sin(30deg) + 2").unwrap()));
+    assert!(is_expensive(&parse("integrate(x, x, 0, 1)").unwrap()));
+    assert!(is_expensive(
+        &parse("2 * (1 - solve(x^2 = 4, x)!)").unwrap()
+    ));
+}
+
+/// Handles the functions whose result isn't a single number when they are not
+/// part of a calculation, e.g. `diff(x^2, x)`
+fn execute_function(
+    expression: &Expression,
+    context: &Context,
+) -> Option<Result<Value, ExecError>> {
+    match *expression {
+        // Symbolic results would print the unparsed part as an expression
+        Expression::FunctionCall(_, ref args) if args.iter().any(contains_error) => {
+            Some(Err(ExecError::Unparsed))
+        }
+        Expression::FunctionCall(ref name, ref args) => match (name.as_str(), args.len()) {
+            ("diff", 2) => Some(symbolic_derivative(args, context).map(Value::Expression)),
+            ("simplify", 1) => Some(Ok(Value::Expression(simplify(args[0].clone())))),
+            ("solve", _) => Some(
+                solve(args, context)
+                    .map(|(variable, solutions)| Value::Solutions(variable, solutions)),
+            ),
+            ("integrate", _) => {
+                Some(integrate(args, context).map(|(value, error)| Value::Estimate(value, error)))
+            }
+            ("plot", _) => Some(plot(args, context).map(Value::Plot)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+fn execute_in_mode(input: &str, angle_mode: AngleMode) -> String {
+    match parse_and_execute(
+        input,
+        &Context {
+            angle_mode,
+            ..Context::new()
+        },
+    ) {
+        Ok(value) => value.to_string(),
+        Err(err) => err.msg,
+    }
+}
+
+#[test]
+fn test_exact_trigonometric_results() {
+    assert_eq!(execute_in_mode("sin(30deg)", AngleMode::Radian), "1/2");
+    assert_eq!(execute_in_mode("cos(30)", AngleMode::Degree), "sqrt(3)/2");
+    assert_eq!(execute_in_mode("-tan(pi/3)", AngleMode::Radian), "-sqrt(3)");
+    assert_eq!(execute_in_mode("sin(100grad)", AngleMode::Degree), "1");
+}
+
+#[test]
+fn test_inexact_trigonometric_results() {
+    assert_eq!(
+        execute_in_mode("sin(0.5)", AngleMode::Radian),
+        "0.479425538604203"
+    );
+    assert_eq!(execute_in_mode("sin(30deg) + 1", AngleMode::Radian), "1.5");
+}
+
+#[test]
+fn test_tiny_and_huge_results() {
+    let tiny = execute_in_mode("1/2^70", AngleMode::Radian);
+    assert_eq!(tiny, "8.470329472543003 * 10^(-22)");
+    assert_eq!(execute_in_mode(&tiny, AngleMode::Radian), tiny);
+
+    assert_eq!(
+        execute_in_mode("sin(1deg)^10", AngleMode::Radian),
+        "2.621520053501597 * 10^(-18)"
+    );
+    assert_eq!(
+        execute_in_mode("2^100", AngleMode::Radian),
+        "1.2676506002282294 * 10^30"
+    );
+}
+
+#[test]
+fn test_angle_literals() {
+    assert_eq!(execute_in_mode("pi rad", AngleMode::Degree), "180");
+    assert_eq!(execute_in_mode("90deg + 100grad", AngleMode::Degree), "180");
+    assert_eq!(execute_in_mode("asin(1)", AngleMode::Gradian), "100");
+}
+
+#[test]
+fn test_angle_results() {
+    let evaluate_str = |input| parse_and_execute(input, &Context::new()).unwrap();
+
+    assert_eq!(
+        evaluate_str("30deg + 10deg"),
+        Value::Angle(40f64, AngleMode::Degree)
+    );
+    assert_eq!(
+        evaluate_str("2 * asin(1)"),
+        Value::Angle(180f64, AngleMode::Degree)
+    );
+    assert_eq!(evaluate_str("asin(1) / 45deg"), Value::Number(2f64));
+    assert_eq!(evaluate_str("2 * 3"), Value::Number(6f64));
+}
+
+#[test]
+fn test_diagnose() {
+    let context = Context::new();
+    let messages = |input| -> Vec<(String, usize)> {
+        diagnose(input, &context)
+            .into_iter()
+            .map(|err| (err.msg, err.pos.start.index))
+            .collect()
+    };
+
+    assert_eq!(messages("2 * 3"), vec![]);
+    assert_eq!(
+        messages("2 × 3 + * 4"),
+        vec![
+            (String::from("× is not an operator, did you mean *?"), 2),
+            (String::from("expected (, identifier or number"), 8),
+        ]
+    );
+    assert_eq!(
+        messages("(1 + 2 3"),
+        vec![
+            (String::from("operator missing between 2 and 3"), 7),
+            (String::from("unclosed ("), 0),
+        ]
+    );
+    assert_eq!(
+        messages("x = 3"),
+        vec![(String::from("expected !, deg, grad, rad or °"), 2)]
+    );
+    assert_eq!(
+        messages("2 ×"),
+        vec![(String::from("× is not an operator, did you mean *?"), 2)]
+    );
+    assert_eq!(
+        messages("1 + foo"),
+        vec![(String::from("unknown identifier foo"), 4)]
+    );
+}
+
+#[test]
+fn test_unparsed_arguments() {
+    use parser::ast::BinaryOperator;
+
+    // `� * 0`, as repaired by `parse_recovering`
+    let product = Expression::BinaryOperation(
+        Box::new(Expression::Error),
+        BinaryOperator::Multiplication,
+        Box::new(Expression::Number(0f64)),
+    );
+    let variable = Expression::Identifier(String::from("x"));
+    let evaluate_call = |name: &str, args: Vec<Expression>| {
+        let call = Expression::FunctionCall(name.to_string(), args);
+        evaluate(&Root::Expression(call), &Context::new())
+    };
+
+    assert_eq!(
+        evaluate_call("simplify", vec![product.clone()]),
+        Err(ExecError::Unparsed)
+    );
+    assert_eq!(
+        evaluate_call("diff", vec![product, variable]),
+        Err(ExecError::Unparsed)
+    );
+    assert_eq!(
+        execute_in_mode("simplify(\u{FFFD} * 0)", AngleMode::Radian),
+        "expected (, ), +, -, identifier or number"
+    );
+}
+
+#[test]
+fn test_semantic_errors() {
+    assert_eq!(
+        execute_in_mode("foo", AngleMode::Degree),
+        "unknown identifier foo"
+    );
+    assert_eq!(
+        execute_in_mode("tan(90)", AngleMode::Degree),
+        "tan(90deg) is undefined"
+    );
+    assert_eq!(
+        execute_in_mode("1.5!", AngleMode::Degree),
+        "1.5! is undefined"
+    );
+    assert_eq!(
+        execute_in_mode("(0-3)!", AngleMode::Degree),
+        "(-3)! is undefined"
+    );
+}
+
+#[test]
+fn test_symbolic_derivative() {
+    assert_eq!(
+        execute_in_mode("diff(x^3 + 2x, x)", AngleMode::Radian),
+        "3x^2 + 2"
+    );
+}
+
+#[test]
+fn test_derivative_at_point() {
+    assert_eq!(
+        execute_in_mode("diff(x^3 + 2x, x, 2)", AngleMode::Radian),
+        "14"
+    );
+    assert_eq!(
+        execute_in_mode("diff(sin(x), x, 0) + 1", AngleMode::Radian),
+        "2"
+    );
+}
+
+#[test]
+fn test_symbolic_derivative_in_calculation() {
+    assert_eq!(
+        execute_in_mode("diff(x^2, x) + 1", AngleMode::Radian),
+        "diff returns an expression and can't be used in a calculation"
+    );
+    assert_eq!(
+        execute_in_mode("diff(x^2, 2)", AngleMode::Radian),
+        "diff expects a variable"
+    );
+}
+
+#[test]
+fn test_simplify() {
+    assert_eq!(
+        execute_in_mode("simplify(x * x + 2x - x + 0)", AngleMode::Radian),
+        "x^2 + x"
+    );
+    assert_eq!(
+        execute_in_mode("simplify(2 + 3) * 2", AngleMode::Radian),
+        "10"
+    );
+}
+
+#[test]
+fn test_solve() {
+    assert_eq!(
+        execute_in_mode("solve(x^2 - 5x + 6 = 0, x)", AngleMode::Radian),
+        "x = 2, x = 3"
+    );
+    assert_eq!(
+        execute_in_mode("solve(2x = 1, x) * 4", AngleMode::Radian),
+        "2"
+    );
+    assert_eq!(
+        execute_in_mode("solve(x^2 = 1, x) + 1", AngleMode::Radian),
+        "solve has 2 solutions and can't be used in a calculation"
+    );
+    assert_eq!(
+        execute_in_mode("solve(x^2 = -1, x)", AngleMode::Radian),
+        "no real solution"
+    );
+    assert_eq!(
+        execute_in_mode("sin(x = 1)", AngleMode::Radian),
+        "equations can only be used in solve"
+    );
+}
+
+#[test]
+fn test_integrate() {
+    assert_eq!(
+        execute_in_mode("integrate(2x, x, 0, 3)", AngleMode::Radian),
+        "9"
+    );
+    assert!(execute_in_mode("integrate(sin(x), x, 0, pi)", AngleMode::Radian).starts_with("2 ± "));
+    assert_eq!(
+        execute_in_mode("integrate(1 / x, x, 0, 1)", AngleMode::Radian),
+        "integrate did not converge"
+    );
+}
+
+#[test]
+fn test_plot() {
+    assert_eq!(
+        execute_in_mode("plot(sin(x), cos(x), -1, 1)", AngleMode::Radian),
+        "plot of sin(x), cos(x) from -1 to 1"
+    );
+    assert_eq!(
+        execute_in_mode("plot(x, 0, 1) + 1", AngleMode::Radian),
+        "plots can't be used in a calculation"
+    );
+}
@@ -0,0 +1,227 @@
+use std::fmt;
+
+use super::context::Context;
+use super::error::ExecError;
+use super::expression::execute_expression;
+use super::value::num_as_string;
+use parser::ast::Expression;
+
+/// The variable plotted functions depend on
+pub const PLOT_VARIABLE: &str = "x";
+
+/// Share of the sampled values that is always visible when scaling the
+/// value axis, the rest may be cut off, e.g. near the poles of `tan(x)`
+const VISIBLE_SHARE: f64 = 0.9;
+/// How many times larger than the visible share the full range of values
+/// may be before the outliers are cut off
+const MAX_OUTLIER_FACTOR: f64 = 4f64;
+/// Space added above and below the values, relative to their range
+const MARGIN: f64 = 0.05;
+
+/// One or more functions of `x` and the range in which they are plotted
+#[derive(Debug, PartialEq, Clone)]
+pub struct Plot {
+    pub functions: Vec<Expression>,
+    pub from: f64,
+    pub to: f64,
+}
+
+impl Plot {
+    /// Evaluates every function at `count` evenly spaced points between
+    /// `from` and `to`, the value is `NaN` where a function is undefined.
+    /// The parsed expressions are reused, so this is cheap enough to be
+    /// called whenever the plot is panned or zoomed.
+    pub fn sample(
+        &self,
+        context: &Context,
+        from: f64,
+        to: f64,
+        count: usize,
+    ) -> Vec<Vec<(f64, f64)>> {
+        let mut scope = context.with_variable(PLOT_VARIABLE, from);
+        let step = (to - from) / (count.max(2) - 1) as f64;
+
+        self.functions
+            .iter()
+            .map(|function| {
+                (0..count)
+                    .map(|i| {
+                        let x = from + step * i as f64;
+                        if let Some(value) = scope.variables.get_mut(PLOT_VARIABLE) {
+                            *value = x;
+                        }
+                        (x, execute_expression(function, &scope).unwrap_or(f64::NAN))
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Plot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "plot of ")?;
+        for (i, function) in self.functions.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", function)?;
+        }
+        write!(
+            f,
+            " from {} to {}",
+            num_as_string(self.from),
+            num_as_string(self.to)
+        )
+    }
+}
+
+/// Handles `plot(f, a, b)` and `plot(f, g, ..., a, b)`
+pub fn plot(args: &[Expression], context: &Context) -> Result<Plot, ExecError> {
+    if args.len() < 3 {
+        return Err(ExecError::WrongArgumentCount {
+            function: String::from("plot"),
+            expected: 3,
+            found: args.len(),
+        });
+    }
+
+    let (functions, range) = args.split_at(args.len() - 2);
+    let a = execute_expression(&range[0], context)?;
+    let b = execute_expression(&range[1], context)?;
+
+    if !a.is_finite() || !b.is_finite() || a == b {
+        return Err(ExecError::Undefined(String::from("the plot range")));
+    }
+
+    // Report errors like unknown identifiers now instead of plotting nothing
+    let scope = context.with_variable(PLOT_VARIABLE, (a + b) / 2f64);
+    for function in functions {
+        match execute_expression(function, &scope) {
+            Ok(_) | Err(ExecError::Undefined(_)) => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(Plot {
+        functions: functions.to_vec(),
+        from: a.min(b),
+        to: a.max(b),
+    })
+}
+
+/// Chooses the range of the value axis so that all finite samples are
+/// visible, unless a few of them are far away from the rest
+pub fn value_range(samples: &[Vec<(f64, f64)>]) -> (f64, f64) {
+    let mut values: Vec<f64> = samples
+        .iter()
+        .flat_map(|series| series.iter().map(|&(_, y)| y))
+        .filter(|y| y.is_finite())
+        .collect();
+
+    if values.is_empty() {
+        return (-1f64, 1f64);
+    }
+
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let cut = ((1f64 - VISIBLE_SHARE) / 2f64 * values.len() as f64) as usize;
+    let (mut min, mut max) = (values[cut], values[values.len() - 1 - cut]);
+    let (lowest, highest) = (values[0], values[values.len() - 1]);
+
+    if highest - lowest <= MAX_OUTLIER_FACTOR * (max - min) {
+        min = lowest;
+        max = highest;
+    }
+
+    if max - min <= f64::EPSILON * max.abs().max(1f64) {
+        return (min - 1f64, max + 1f64);
+    }
+
+    let margin = (max - min) * MARGIN;
+    (min - margin, max + margin)
+}
+
+#[cfg(test)]
+fn plot_input(input: &str) -> Result<Plot, ExecError> {
+    use parser::ast::Root;
+    use parser::parse;
+
+    let context = Context {
+        angle_mode: super::context::AngleMode::Radian,
+        ..Context::new()
+    };
+
+    match parse(input) {
+        Ok(Root::Expression(Expression::FunctionCall(_, args))) => plot(&args, &context),
+        other => panic!("expected a function call: {:?}", other),
+    }
+}
+
+#[test]
+fn test_plot_multiple_functions() {
+    let plot = plot_input("plot(sin(x), cos(x), 1, -1)").unwrap();
+    assert_eq!(plot.functions.len(), 2);
+    assert_eq!((plot.from, plot.to), (-1f64, 1f64));
+}
+
+#[test]
+fn test_plot_errors() {
+    assert_eq!(
+        plot_input("plot(y, 0, 1)"),
+        Err(ExecError::UnknownIdentifier(String::from("y")))
+    );
+    assert_eq!(
+        plot_input("plot(x, 1, 1)"),
+        Err(ExecError::Undefined(String::from("the plot range")))
+    );
+    assert_eq!(
+        plot_input("plot(x, 1)"),
+        Err(ExecError::WrongArgumentCount {
+            function: String::from("plot"),
+            expected: 3,
+            found: 2,
+        })
+    );
+}
+
+#[test]
+fn test_plot_sample() {
+    let plot = plot_input("plot(x^2, sqrt(x), -1, 1)").unwrap();
+    let samples = plot.sample(&Context::new(), -2f64, 2f64, 5);
+
+    assert_eq!(
+        samples[0],
+        vec![
+            (-2f64, 4f64),
+            (-1f64, 1f64),
+            (0f64, 0f64),
+            (1f64, 1f64),
+            (2f64, 4f64)
+        ]
+    );
+    assert!(samples[1][0].1.is_nan());
+    assert_eq!(samples[1][4], (2f64, 2f64.sqrt()));
+}
+
+#[test]
+fn test_value_range() {
+    let samples = vec![vec![(0f64, 0f64), (1f64, 10f64), (2f64, f64::NAN)]];
+    assert_eq!(value_range(&samples), (-0.5, 10.5));
+    assert_eq!(value_range(&[vec![(0f64, 3f64)]]), (2f64, 4f64));
+    assert_eq!(value_range(&[]), (-1f64, 1f64));
+}
+
+#[test]
+fn test_value_range_cuts_off_poles() {
+    let context = Context {
+        angle_mode: super::context::AngleMode::Radian,
+        ..Context::new()
+    };
+    let plot = plot_input("plot(tan(x), -3, 3)").unwrap();
+    let samples = plot.sample(&context, -3f64, 3f64, 601);
+    let (min, max) = value_range(&samples);
+
+    assert!(min > -100f64 && min < -2f64, "{}", min);
+    assert!(max < 100f64 && max > 2f64, "{}", max);
+}
@@ -0,0 +1,363 @@
+use parser::ast::{BinaryOperator, Expression, PrefixOperator};
+
+use self::BinaryOperator::*;
+use self::Expression::{BinaryOperation, Number, UnaryPrefixOperation};
+
+/// Upper bound for the rewrite passes, every pass usually only reaches one
+/// level deeper into nested sums and products
+const MAX_PASSES: usize = 16;
+
+/// Returns an equivalent expression with operations on numbers folded,
+/// neutral elements removed, like terms and factors combined and double
+/// negations removed, e.g. `x * x + 2x - x + 0` becomes `x^2 + x`. Nothing
+/// is assumed about the variables, so `x / x` and `0 * x` are kept since they
+/// are undefined for `x = 0` and `x = inf`.
+pub fn simplify(expression: Expression) -> Expression {
+    let mut current = expression;
+
+    for _ in 0..MAX_PASSES {
+        let next = simplify_once(current.clone());
+        if next == current {
+            break;
+        }
+        current = next;
+    }
+
+    current
+}
+
+fn simplify_once(expression: Expression) -> Expression {
+    match expression {
+        BinaryOperation(left, op, right) => {
+            simplify_binary_operation(simplify_once(*left), op, simplify_once(*right))
+        }
+        UnaryPrefixOperation(op, exp) => simplify_prefix_operation(op, simplify_once(*exp)),
+        Expression::UnarySuffixOperation(exp, op) => {
+            Expression::UnarySuffixOperation(Box::new(simplify_once(*exp)), op)
+        }
+        Expression::FunctionCall(name, args) => {
+            Expression::FunctionCall(name, args.into_iter().map(simplify_once).collect())
+        }
+        Expression::Equation(left, right) => Expression::Equation(
+            Box::new(simplify_once(*left)),
+            Box::new(simplify_once(*right)),
+        ),
+        exp => exp,
+    }
+}
+
+fn simplify_binary_operation(
+    left: Expression,
+    op: BinaryOperator,
+    right: Expression,
+) -> Expression {
+    if let (&Number(l), &Number(r)) = (&left, &right) {
+        if let Some(n) = fold(l, op, r) {
+            return Number(n);
+        }
+    }
+
+    match (left, op, right) {
+        (Number(0.0), Plus, exp) | (exp, Plus, Number(0.0)) => exp,
+        (exp, Minus, Number(0.0)) => exp,
+        (Number(0.0), Minus, exp) => simplify_prefix_operation(PrefixOperator::MinusSign, exp),
+        (Number(1.0), Multiplication, exp) | (exp, Multiplication, Number(1.0)) => exp,
+        (exp, Divide, Number(1.0)) => exp,
+        // `0 / x` is undefined for `x = 0`
+        (Number(0.0), Divide, Number(d)) if d != 0f64 => Number(0f64),
+        (_, Power, Number(0.0)) => Number(1f64),
+        (exp, Power, Number(1.0)) => exp,
+        (Number(1.0), Power, _) => Number(1f64),
+        (left, Divide, right) => match split_coefficient(left) {
+            // Pull the sign out of the numerator, `-x / y` reads better than `(-x) / y`
+            (c, term) if c < 0f64 => neg(div(build_product(-c, term), right)),
+            (c, term) => div(build_product(c, term), right),
+        },
+        (left, Plus, right) => combine_like_terms(add(left, right)),
+        (left, Minus, right) => combine_like_terms(sub(left, right)),
+        (left, Multiplication, right) => {
+            let mut coefficient = 1f64;
+            let mut factors = Vec::new();
+            collect_factors(mul(left, right), &mut coefficient, &mut factors);
+            build_product(coefficient, factors)
+        }
+        (left, op, right) => BinaryOperation(Box::new(left), op, Box::new(right)),
+    }
+}
+
+fn simplify_prefix_operation(op: PrefixOperator, exp: Expression) -> Expression {
+    match (op, exp) {
+        (PrefixOperator::PlusSign, exp) => exp,
+        (PrefixOperator::MinusSign, Number(n)) => Number(-n),
+        (PrefixOperator::MinusSign, UnaryPrefixOperation(PrefixOperator::MinusSign, exp)) => *exp,
+        (op, exp) => UnaryPrefixOperation(op, Box::new(exp)),
+    }
+}
+
+/// Only folds operations with whole numbered results, so `1 / 3` and `2^0.5`
+/// are kept as they are
+fn fold(left: f64, op: BinaryOperator, right: f64) -> Option<f64> {
+    let result = match op {
+        Plus => left + right,
+        Minus => left - right,
+        Multiplication => left * right,
+        Divide if (left / right).fract() == 0f64 => left / right,
+        Power if right >= 0f64 && right.fract() == 0f64 => left.powf(right),
+        Divide | Power => return None,
+    };
+
+    if result.is_finite() {
+        Some(result)
+    } else {
+        None
+    }
+}
+
+/// A sum as a list of terms and their numeric coefficients, constants are
+/// stored as coefficients of the term `1`
+type Terms = Vec<(Expression, f64)>;
+
+/// A product as a list of bases and their exponents
+type Factors = Vec<(Expression, Expression)>;
+
+fn combine_like_terms(sum: Expression) -> Expression {
+    let mut terms = Terms::new();
+    collect_terms(sum, 1f64, &mut terms);
+
+    // Constants go last, e.g. `x + 2`
+    let (constants, mut terms): (Terms, Terms) = terms
+        .into_iter()
+        .partition(|(term, _)| *term == Number(1f64));
+    terms.extend(constants);
+
+    let mut sum: Option<Expression> = None;
+
+    for (term, coefficient) in terms {
+        // Cancelled terms stay, `x - x` is undefined for `x = inf`
+        if coefficient == 0f64 && term == Number(1f64) {
+            continue;
+        }
+
+        let magnitude = match term {
+            Number(_) => Number(coefficient.abs()),
+            term if coefficient.abs() == 1f64 => term,
+            term => mul(Number(coefficient.abs()), term),
+        };
+
+        sum = Some(match (sum, coefficient < 0f64) {
+            (None, false) => magnitude,
+            (None, true) => simplify_prefix_operation(PrefixOperator::MinusSign, magnitude),
+            (Some(sum), false) => add(sum, magnitude),
+            (Some(sum), true) => sub(sum, magnitude),
+        });
+    }
+
+    sum.unwrap_or(Number(0f64))
+}
+
+fn collect_terms(expression: Expression, sign: f64, terms: &mut Terms) {
+    match expression {
+        BinaryOperation(left, Plus, right) => {
+            collect_terms(*left, sign, terms);
+            collect_terms(*right, sign, terms);
+        }
+        BinaryOperation(left, Minus, right) => {
+            collect_terms(*left, sign, terms);
+            collect_terms(*right, -sign, terms);
+        }
+        exp => {
+            let (coefficient, factors) = split_coefficient(exp);
+            let term = match build_product(1f64, factors) {
+                Number(n) => return add_term(terms, Number(1f64), sign * coefficient * n),
+                term => term,
+            };
+            add_term(terms, term, sign * coefficient);
+        }
+    }
+}
+
+fn add_term(terms: &mut Terms, term: Expression, coefficient: f64) {
+    for &mut (ref existing, ref mut sum) in terms.iter_mut() {
+        if *existing == term {
+            *sum += coefficient;
+            return;
+        }
+    }
+    terms.push((term, coefficient));
+}
+
+/// Splits a product into its numeric coefficient and the remaining factors
+fn split_coefficient(expression: Expression) -> (f64, Factors) {
+    let mut coefficient = 1f64;
+    let mut factors = Factors::new();
+    collect_factors(expression, &mut coefficient, &mut factors);
+    (coefficient, factors)
+}
+
+fn collect_factors(expression: Expression, coefficient: &mut f64, factors: &mut Factors) {
+    match expression {
+        BinaryOperation(left, Multiplication, right) => {
+            collect_factors(*left, coefficient, factors);
+            collect_factors(*right, coefficient, factors);
+        }
+        UnaryPrefixOperation(PrefixOperator::MinusSign, exp) => {
+            *coefficient = -*coefficient;
+            collect_factors(*exp, coefficient, factors);
+        }
+        Number(n) => *coefficient *= n,
+        BinaryOperation(base, Power, exponent) => add_factor(factors, *base, *exponent),
+        exp => add_factor(factors, exp, Number(1f64)),
+    }
+}
+
+/// Like bases are combined by adding their exponents, `x * x^2` is `x^(1 + 2)`
+fn add_factor(factors: &mut Factors, base: Expression, exponent: Expression) {
+    for &mut (ref existing, ref mut sum) in factors.iter_mut() {
+        if *existing == base {
+            *sum = add(sum.clone(), exponent);
+            return;
+        }
+    }
+    factors.push((base, exponent));
+}
+
+fn build_product(coefficient: f64, factors: Factors) -> Expression {
+    let mut product = if coefficient.abs() == 1f64 {
+        None
+    } else {
+        Some(Number(coefficient.abs()))
+    };
+
+    for (base, exponent) in factors {
+        let factor = match exponent {
+            Number(0.0) => continue,
+            Number(1.0) => base,
+            exponent => pow(base, exponent),
+        };
+
+        product = Some(match product {
+            Some(product) => mul(product, factor),
+            None => factor,
+        });
+    }
+
+    let product = product.unwrap_or(Number(1f64));
+
+    if coefficient < 0f64 {
+        simplify_prefix_operation(PrefixOperator::MinusSign, product)
+    } else {
+        product
+    }
+}
+
+fn add(left: Expression, right: Expression) -> Expression {
+    BinaryOperation(Box::new(left), Plus, Box::new(right))
+}
+
+fn sub(left: Expression, right: Expression) -> Expression {
+    BinaryOperation(Box::new(left), Minus, Box::new(right))
+}
+
+fn mul(left: Expression, right: Expression) -> Expression {
+    BinaryOperation(Box::new(left), Multiplication, Box::new(right))
+}
+
+fn div(left: Expression, right: Expression) -> Expression {
+    BinaryOperation(Box::new(left), Divide, Box::new(right))
+}
+
+fn pow(left: Expression, right: Expression) -> Expression {
+    BinaryOperation(Box::new(left), Power, Box::new(right))
+}
+
+fn neg(exp: Expression) -> Expression {
+    UnaryPrefixOperation(PrefixOperator::MinusSign, Box::new(exp))
+}
+
+#[cfg(test)]
+fn parse_and_simplify(input: &str) -> String {
+    use parser::ast::Root;
+    use parser::parse;
+
+    match parse(input) {
+        Ok(Root::Expression(exp)) => simplify(exp).to_string(),
+        Err(err) => panic!("failed to parse {}: {:?}", input, err),
+    }
+}
+
+#[test]
+fn test_simplify_folds_numbers() {
+    assert_eq!(parse_and_simplify("1 + 2 * 3"), "7");
+    assert_eq!(parse_and_simplify("6 / 3"), "2");
+    assert_eq!(parse_and_simplify("x^(3 - 1)"), "x^2");
+}
+
+#[test]
+fn test_simplify_keeps_inexact_division() {
+    assert_eq!(parse_and_simplify("1 / 3"), "1 / 3");
+}
+
+#[test]
+fn test_simplify_removes_neutral_elements() {
+    assert_eq!(parse_and_simplify("x + 0"), "x");
+    assert_eq!(parse_and_simplify("0 + x"), "x");
+    assert_eq!(parse_and_simplify("x - 0"), "x");
+    assert_eq!(parse_and_simplify("1 * x"), "x");
+    assert_eq!(parse_and_simplify("x / 1"), "x");
+    assert_eq!(parse_and_simplify("x^1"), "x");
+}
+
+#[test]
+fn test_simplify_absorbing_elements() {
+    assert_eq!(parse_and_simplify("0 * 5"), "0");
+    assert_eq!(parse_and_simplify("x^0"), "1");
+    assert_eq!(parse_and_simplify("0 - x"), "-x");
+    assert_eq!(parse_and_simplify("0 / 2"), "0");
+}
+
+#[test]
+fn test_simplify_keeps_undefined_operations() {
+    assert_eq!(parse_and_simplify("x / x"), "x / x");
+    assert_eq!(parse_and_simplify("0 * sin(x)"), "0sin(x)");
+    assert_eq!(parse_and_simplify("x * 0"), "0x");
+    assert_eq!(parse_and_simplify("x - x"), "0x");
+}
+
+#[test]
+fn test_simplify_keeps_division_by_zero() {
+    assert_eq!(parse_and_simplify("0 / 0"), "0 / 0");
+    assert_eq!(parse_and_simplify("0 / x"), "0 / x");
+}
+
+#[test]
+fn test_simplify_combines_like_terms() {
+    assert_eq!(parse_and_simplify("x + x"), "2x");
+    assert_eq!(parse_and_simplify("2x + 3 + 3x - 1"), "5x + 2");
+    assert_eq!(parse_and_simplify("2x + 1 - x - 1"), "x");
+    assert_eq!(parse_and_simplify("2sin(x) - sin(x)"), "sin(x)");
+}
+
+#[test]
+fn test_simplify_combines_like_factors() {
+    assert_eq!(parse_and_simplify("x * x"), "x^2");
+    assert_eq!(parse_and_simplify("x^2 * 3 * x"), "3x^3");
+    assert_eq!(parse_and_simplify("2 * y * 3"), "6y");
+}
+
+#[test]
+fn test_simplify_double_negation() {
+    assert_eq!(parse_and_simplify("--x"), "x");
+    assert_eq!(parse_and_simplify("-(-(-x))"), "-x");
+}
+
+#[test]
+fn test_simplify_pulls_sign_out_of_fraction() {
+    assert_eq!(parse_and_simplify("(0 - 1) / x"), "-1 / x");
+    assert_eq!(parse_and_simplify("-x / 2"), "-x / 2");
+}
+
+#[test]
+fn test_simplify_nested() {
+    assert_eq!(parse_and_simplify("sin(x + x) + 0 * y"), "sin(2x) + 0y");
+    assert_eq!(parse_and_simplify("(x + 1) * (x + 1)"), "(x + 1)^2");
+}
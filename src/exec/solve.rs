@@ -0,0 +1,509 @@
+use std::f64::consts::PI;
+
+use super::context::Context;
+use super::derivative::differentiate;
+use super::error::ExecError;
+use super::expression::{contains_variable, execute_expression};
+use parser::ast::{BinaryOperator, Expression, PrefixOperator};
+
+/// Range searched for solutions of equations that can't be solved in closed
+/// form, unless the range is given explicitly
+const SEARCH_RANGE: (f64, f64) = (-1000f64, 1000f64);
+const SEARCH_STEPS: usize = 20_000;
+const MAX_ITERATIONS: usize = 100;
+/// Highest exponent expanded when treating an expression as polynomial
+const MAX_DEGREE: f64 = 64f64;
+
+/// Handles `solve(equation, x)` and `solve(equation, x, a, b)`, the latter
+/// only reports solutions between `a` and `b`. Returns the variable and its
+/// solutions in ascending order.
+pub fn solve(args: &[Expression], context: &Context) -> Result<(String, Vec<f64>), ExecError> {
+    if args.len() != 2 && args.len() != 4 {
+        return Err(ExecError::WrongArgumentCount {
+            function: String::from("solve"),
+            expected: if args.len() < 2 { 2 } else { 4 },
+            found: args.len(),
+        });
+    }
+
+    let variable = match args[1] {
+        Expression::Identifier(ref name) => name,
+        _ => return Err(ExecError::ExpectedVariable(String::from("solve"))),
+    };
+
+    // `a = b` is solved as `a - b = 0`
+    let function = match args[0] {
+        Expression::Equation(ref left, ref right) => {
+            Expression::BinaryOperation(left.clone(), BinaryOperator::Minus, right.clone())
+        }
+        ref exp => exp.clone(),
+    };
+
+    let range = if args.len() == 4 {
+        let a = execute_expression(&args[2], context)?;
+        let b = execute_expression(&args[3], context)?;
+        Some((a.min(b), a.max(b)))
+    } else {
+        None
+    };
+
+    // E.g. `x / 0 = 1`
+    let undefined = || ExecError::Undefined(args[0].to_string());
+
+    let closed_form = match polynomial_coefficients(&function, variable, context)? {
+        Some(ref coefficients) if coefficients.iter().any(|c| !c.value.is_finite()) => {
+            return Err(undefined())
+        }
+        Some(coefficients) => solve_polynomial(coefficients)?,
+        None => None,
+    };
+
+    let mut solutions = match (closed_form, range) {
+        (Some(solutions), Some((a, b))) => solutions
+            .into_iter()
+            .filter(|x| *x >= a && *x <= b)
+            .collect(),
+        (Some(solutions), None) => solutions,
+        (None, range) => find_roots(&function, variable, context, range.unwrap_or(SEARCH_RANGE))?,
+    };
+
+    if solutions.is_empty() {
+        return Err(ExecError::NoRealSolution);
+    }
+    if solutions.iter().any(|x| !x.is_finite()) {
+        return Err(undefined());
+    }
+
+    // Hide rounding errors of the closed form solutions, e.g. 2.0000000000000004,
+    // but keep small solutions like 10^(-12) that aren't whole numbers
+    let residual = |x: f64| {
+        execute_expression(&function, &context.with_variable(variable, x))
+            .map_or(f64::INFINITY, f64::abs)
+    };
+    for x in &mut solutions {
+        let rounded = x.round();
+        if (*x - rounded).abs() < 1e-10 && residual(rounded) <= residual(*x) {
+            *x = rounded;
+        }
+    }
+    solutions.sort_by(|a, b| a.total_cmp(b));
+    solutions.dedup_by(|a, b| (*a - *b).abs() <= 1e-10 * a.abs().max(1f64));
+
+    Ok((variable.clone(), solutions))
+}
+
+/// A coefficient of a polynomial and the sum of the magnitudes of the terms
+/// it was computed from. A coefficient that is tiny compared to its terms is
+/// only left over from rounding errors of terms that cancelled.
+#[derive(Debug, Copy, Clone)]
+struct Coefficient {
+    value: f64,
+    magnitude: f64,
+}
+
+impl Coefficient {
+    fn new(value: f64) -> Coefficient {
+        Coefficient {
+            value,
+            magnitude: value.abs(),
+        }
+    }
+
+    fn is_zero(self) -> bool {
+        self.value.abs() <= 4f64 * f64::EPSILON * self.magnitude
+    }
+}
+
+/// Returns the coefficients of the expression as polynomial in `variable`,
+/// starting with the constant term, or `None` if it isn't a polynomial
+fn polynomial_coefficients(
+    expression: &Expression,
+    variable: &str,
+    context: &Context,
+) -> Result<Option<Vec<Coefficient>>, ExecError> {
+    if !contains_variable(expression, variable) {
+        let value = execute_expression(expression, context)?;
+        return Ok(Some(vec![Coefficient::new(value)]));
+    }
+
+    let coefficients = match *expression {
+        Expression::Identifier(_) => vec![Coefficient::new(0f64), Coefficient::new(1f64)],
+        Expression::UnaryPrefixOperation(op, ref exp) => {
+            match polynomial_coefficients(exp, variable, context)? {
+                Some(coefficients) => match op {
+                    PrefixOperator::PlusSign => coefficients,
+                    PrefixOperator::MinusSign => coefficients
+                        .iter()
+                        .map(|c| Coefficient {
+                            value: -c.value,
+                            magnitude: c.magnitude,
+                        })
+                        .collect(),
+                },
+                None => return Ok(None),
+            }
+        }
+        Expression::BinaryOperation(ref left, op, ref right) => {
+            let left = match polynomial_coefficients(left, variable, context)? {
+                Some(left) => left,
+                None => return Ok(None),
+            };
+
+            if op == BinaryOperator::Power {
+                let exponent = match polynomial_coefficients(right, variable, context)? {
+                    Some(ref exponent) if exponent.len() == 1 => exponent[0].value,
+                    _ => return Ok(None),
+                };
+                if exponent < 0f64 || exponent.fract() != 0f64 || exponent > MAX_DEGREE {
+                    return Ok(None);
+                }
+
+                let mut result = vec![Coefficient::new(1f64)];
+                for _ in 0..exponent as usize {
+                    result = multiply_polynomials(&result, &left);
+                }
+                result
+            } else {
+                let right = match polynomial_coefficients(right, variable, context)? {
+                    Some(right) => right,
+                    None => return Ok(None),
+                };
+
+                match op {
+                    BinaryOperator::Plus => add_polynomials(&left, &right, 1f64),
+                    BinaryOperator::Minus => add_polynomials(&left, &right, -1f64),
+                    BinaryOperator::Multiplication => multiply_polynomials(&left, &right),
+                    BinaryOperator::Divide if right.len() == 1 => {
+                        let divisor = right[0].value;
+                        left.iter()
+                            .map(|c| Coefficient {
+                                value: c.value / divisor,
+                                magnitude: c.magnitude / divisor.abs(),
+                            })
+                            .collect()
+                    }
+                    _ => return Ok(None),
+                }
+            }
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(coefficients))
+}
+
+fn add_polynomials(left: &[Coefficient], right: &[Coefficient], sign: f64) -> Vec<Coefficient> {
+    let zero = Coefficient::new(0f64);
+
+    (0..left.len().max(right.len()))
+        .map(|i| {
+            let (l, r) = (left.get(i).unwrap_or(&zero), right.get(i).unwrap_or(&zero));
+            Coefficient {
+                value: l.value + sign * r.value,
+                magnitude: l.magnitude + r.magnitude,
+            }
+        })
+        .collect()
+}
+
+fn multiply_polynomials(left: &[Coefficient], right: &[Coefficient]) -> Vec<Coefficient> {
+    let mut result = vec![Coefficient::new(0f64); left.len() + right.len() - 1];
+    for (i, l) in left.iter().enumerate() {
+        for (j, r) in right.iter().enumerate() {
+            result[i + j].value += l.value * r.value;
+            result[i + j].magnitude += l.magnitude * r.magnitude;
+        }
+    }
+    result
+}
+
+/// Solves polynomials up to degree three in closed form, returns `None` for
+/// polynomials of higher degree
+fn solve_polynomial(mut coefficients: Vec<Coefficient>) -> Result<Option<Vec<f64>>, ExecError> {
+    // E.g. the coefficient of x^2 in `(0.1 + 0.2)x^2 - 0.3x^2`
+    while coefficients.last().is_some_and(|c| c.is_zero()) {
+        coefficients.pop();
+    }
+
+    let c: Vec<f64> = coefficients.iter().map(|c| c.value).collect();
+    let solutions = match c.len() {
+        0 => return Err(ExecError::InfinitelyManySolutions),
+        1 => vec![],
+        2 => vec![-c[0] / c[1]],
+        3 => solve_quadratic(c[2], c[1], c[0]),
+        4 => solve_cubic(c[3], c[2], c[1], c[0]),
+        _ => return Ok(None),
+    };
+
+    Ok(Some(solutions))
+}
+
+fn solve_quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    let discriminant = b * b - 4f64 * a * c;
+
+    if discriminant < 0f64 {
+        vec![]
+    } else if discriminant == 0f64 {
+        vec![-b / (2f64 * a)]
+    } else {
+        // Avoids the cancellation in `-b + sqrt(discriminant)` for large `b`
+        let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+        if q == 0f64 {
+            let x = (-c / a).sqrt();
+            vec![-x, x]
+        } else {
+            vec![q / a, c / q]
+        }
+    }
+}
+
+fn solve_cubic(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    // Substituting `x = t - b / 3a` gives the depressed cubic `t^3 + pt + q`
+    let shift = b / (3f64 * a);
+    let p = (3f64 * a * c - b * b) / (3f64 * a * a);
+    let q = (2f64 * b * b * b - 9f64 * a * b * c + 27f64 * a * a * d) / (27f64 * a * a * a);
+
+    let discriminant = q * q / 4f64 + p * p * p / 27f64;
+    let epsilon = 1e-14
+        * (q * q / 4f64)
+            .abs()
+            .max((p * p * p / 27f64).abs())
+            .max(1e-300);
+
+    let roots = if p == 0f64 {
+        vec![(-q).cbrt()]
+    } else if discriminant.abs() <= epsilon {
+        vec![3f64 * q / p, -3f64 * q / (2f64 * p)]
+    } else if discriminant > 0f64 {
+        let sqrt = discriminant.sqrt();
+        vec![(-q / 2f64 + sqrt).cbrt() + (-q / 2f64 - sqrt).cbrt()]
+    } else {
+        let r = 2f64 * (-p / 3f64).sqrt();
+        let phi = (3f64 * q / (p * r)).acos() / 3f64;
+        (0..3)
+            .map(|k| r * (phi - 2f64 * PI * f64::from(k) / 3f64).cos())
+            .collect()
+    };
+
+    roots.into_iter().map(|t| t - shift).collect()
+}
+
+/// Finds the roots of the function by scanning the range for sign changes
+/// and refining each bracket with Newton's method, falling back to bisection
+/// whenever a Newton step leaves the bracket
+fn find_roots(
+    function: &Expression,
+    variable: &str,
+    context: &Context,
+    range: (f64, f64),
+) -> Result<Vec<f64>, ExecError> {
+    let derivative = differentiate(function, variable, context.angle_mode).ok();
+    let mut scope = context.with_variable(variable, (range.0 + range.1) / 2f64);
+
+    // Report errors like unknown identifiers instead of treating them as no
+    // solution. Errors that depend on the value, e.g. of `x!` for `x = 0.5`,
+    // only mean that there is no root at this point.
+    match execute_expression(function, &scope) {
+        Err(ExecError::Undefined(_)) | Err(ExecError::TooLarge(_)) | Ok(_) => {}
+        Err(err) => return Err(err),
+    }
+
+    let mut evaluate = |expression: &Expression, x: f64| -> f64 {
+        if let Some(value) = scope.variables.get_mut(variable) {
+            *value = x;
+        }
+        execute_expression(expression, &scope).unwrap_or(f64::NAN)
+    };
+
+    let step = (range.1 - range.0) / SEARCH_STEPS as f64;
+    let mut roots = Vec::new();
+    let mut previous_value = f64::NAN;
+    let mut lower = range.0;
+    let mut lower_value = evaluate(function, lower);
+
+    for i in 1..=SEARCH_STEPS {
+        let upper = range.0 + step * i as f64;
+        let upper_value = evaluate(function, upper);
+
+        // Ignores regions that are zero due to underflow, e.g. `e^x` for `x < -745`
+        if lower_value == 0f64 && previous_value != 0f64 && upper_value != 0f64 {
+            roots.push(lower);
+        } else if lower_value * upper_value < 0f64 {
+            let mut bracket = (lower, upper);
+            let mut x = (lower + upper) / 2f64;
+
+            for _ in 0..MAX_ITERATIONS {
+                let value = evaluate(function, x);
+                if value == 0f64 {
+                    break;
+                }
+
+                if value.signum() == lower_value.signum() {
+                    bracket.0 = x;
+                } else {
+                    bracket.1 = x;
+                }
+
+                let newton = derivative
+                    .as_ref()
+                    .map(|derivative| x - value / evaluate(derivative, x))
+                    .filter(|next| *next > bracket.0 && *next < bracket.1);
+                let next = newton.unwrap_or((bracket.0 + bracket.1) / 2f64);
+
+                if (next - x).abs() <= f64::EPSILON * x.abs().max(1f64) {
+                    x = next;
+                    break;
+                }
+                x = next;
+            }
+
+            // Sign changes at poles, e.g. of `tan(x)`, aren't roots
+            if evaluate(function, x).abs() < 1e-6 {
+                roots.push(x);
+            }
+        }
+
+        previous_value = lower_value;
+        lower = upper;
+        lower_value = upper_value;
+    }
+
+    if lower_value == 0f64 && previous_value != 0f64 {
+        roots.push(lower);
+    }
+
+    Ok(roots)
+}
+
+#[cfg(test)]
+fn solve_input(input: &str) -> Result<Vec<f64>, ExecError> {
+    use parser::ast::Root;
+    use parser::parse;
+
+    let context = Context {
+        angle_mode: super::context::AngleMode::Radian,
+        ..Context::new()
+    };
+
+    match parse(input) {
+        Ok(Root::Expression(Expression::FunctionCall(_, args))) => {
+            solve(&args, &context).map(|(_, solutions)| solutions)
+        }
+        other => panic!("expected a function call: {:?}", other),
+    }
+}
+
+#[cfg(test)]
+fn assert_solutions(input: &str, expected: &[f64]) {
+    let solutions = solve_input(input).unwrap();
+    assert_eq!(solutions.len(), expected.len(), "{:?}", solutions);
+    for (solution, expected) in solutions.iter().zip(expected) {
+        assert!((solution - expected).abs() < 1e-9, "{:?}", solutions);
+    }
+}
+
+#[test]
+fn test_solve_linear() {
+    assert_eq!(solve_input("solve(2x + 4 = 0, x)"), Ok(vec![-2f64]));
+    assert_eq!(solve_input("solve(3 = (x - 1) / 2, x)"), Ok(vec![7f64]));
+}
+
+#[test]
+fn test_solve_quadratic() {
+    assert_eq!(
+        solve_input("solve(x^2 - 5x + 6 = 0, x)"),
+        Ok(vec![2f64, 3f64])
+    );
+    assert_eq!(solve_input("solve(x^2 = 4, x)"), Ok(vec![-2f64, 2f64]));
+    assert_eq!(solve_input("solve((x - 1)^2, x)"), Ok(vec![1f64]));
+}
+
+#[test]
+fn test_solve_cubic() {
+    assert_eq!(
+        solve_input("solve(x^3 - 6x^2 + 11x - 6 = 0, x)"),
+        Ok(vec![1f64, 2f64, 3f64])
+    );
+    assert_eq!(solve_input("solve(x^3 = 8, x)"), Ok(vec![2f64]));
+    assert_eq!(solve_input("solve(x^3 - 3x + 2, x)"), Ok(vec![-2f64, 1f64]));
+}
+
+#[test]
+fn test_solve_numerically() {
+    assert_solutions("solve(cos(x) = x, x)", &[0.7390851332151607]);
+    assert_solutions("solve(x^4 - 5x^2 + 4 = 0, x)", &[-2f64, -1f64, 1f64, 2f64]);
+    assert_solutions("solve(sin(x), x, -4, 4)", &[-PI, 0f64, PI]);
+}
+
+#[test]
+fn test_solve_ignores_poles() {
+    assert_solutions("solve(tan(x), x, 1, 4)", &[PI]);
+}
+
+#[test]
+fn test_solve_without_solution() {
+    assert_eq!(
+        solve_input("solve(x^2 + 1 = 0, x)"),
+        Err(ExecError::NoRealSolution)
+    );
+    assert_eq!(
+        solve_input("solve(e^x = 0, x)"),
+        Err(ExecError::NoRealSolution)
+    );
+    assert_eq!(
+        solve_input("solve(x = x + 1, x)"),
+        Err(ExecError::NoRealSolution)
+    );
+}
+
+#[test]
+fn test_solve_undefined() {
+    assert_eq!(
+        solve_input("solve(x / 0 = 1, x)"),
+        Err(ExecError::Undefined(String::from("x / 0 = 1")))
+    );
+    assert_eq!(
+        solve_input("solve(0x = 1 / 0, x)"),
+        Err(ExecError::Undefined(String::from("0x = 1 / 0")))
+    );
+}
+
+#[test]
+fn test_solve_ignores_rounding_errors_of_leading_coefficient() {
+    // The coefficient of x^2 is 5.6e-17 instead of 0
+    assert_eq!(
+        solve_input("solve((0.1 + 0.2)x^2 - 0.3x^2 + x = 1, x)"),
+        Ok(vec![1f64])
+    );
+}
+
+#[test]
+fn test_solve_small_coefficients_and_solutions() {
+    assert_eq!(solve_input("solve(x / 10^17 = 1, x)"), Ok(vec![1e17]));
+    assert_eq!(solve_input("solve(10^12 x = 1, x)"), Ok(vec![1e-12]));
+    assert_eq!(
+        solve_input("solve(x = 0.00000000001, x)"),
+        Ok(vec![0.00000000001])
+    );
+}
+
+#[test]
+fn test_solve_with_errors_at_some_points() {
+    assert_solutions("solve(x! = 120, x)", &[5f64]);
+}
+
+#[test]
+fn test_solve_identity() {
+    assert_eq!(
+        solve_input("solve(2x = x + x, x)"),
+        Err(ExecError::InfinitelyManySolutions)
+    );
+}
+
+#[test]
+fn test_solve_reports_unknown_identifiers() {
+    assert_eq!(
+        solve_input("solve(sin(x) = y, x)"),
+        Err(ExecError::UnknownIdentifier(String::from("y")))
+    );
+}
@@ -0,0 +1,106 @@
+use std::fmt;
+
+use super::context::AngleMode;
+use super::exact::Exact;
+use super::plot::Plot;
+use parser::ast::Expression;
+
+/// The result of evaluating an input
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    Number(f64),
+    /// A number that is an angle in the angle mode, e.g. `asin(1)`
+    Angle(f64, AngleMode),
+    Exact(Exact),
+    /// A symbolic result, e.g. of `diff(x^2, x)`
+    Expression(Expression),
+    /// The solutions of an equation for a variable
+    Solutions(String, Vec<f64>),
+    /// A numerical approximation and an estimate of its absolute error
+    Estimate(f64, f64),
+    Plot(Plot),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Number(n) | Value::Angle(n, _) => write!(f, "{}", num_as_string(n)),
+            Value::Exact(exact) => write!(f, "{}", exact),
+            Value::Expression(ref exp) => write!(f, "{}", exp),
+            Value::Solutions(ref variable, ref solutions) => {
+                for (i, solution) in solutions.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{} = {}", variable, num_as_string(*solution))?;
+                }
+                Ok(())
+            }
+            Value::Estimate(value, 0.0) => write!(f, "{}", num_as_string(value)),
+            Value::Plot(ref plot) => write!(f, "{}", plot),
+            Value::Estimate(value, error) => {
+                write!(f, "{} ± {:.1e}", num_as_string(value), error)
+            }
+        }
+    }
+}
+
+pub fn num_as_string(num: f64) -> String {
+    fn decimal_digits(num: f64) -> Option<usize> {
+        (0..20u32)
+            .find(|&n| (num * 10usize.pow(n) as f64) % 1f64 == 0f64)
+            .map(|n| n as usize)
+    }
+
+    if !num.is_finite() {
+        return num.to_string();
+    }
+
+    // Very large and very small magnitudes would be written with dozens of
+    // digits. `1e-7` would be read back as `1 * e - 7`, so this is written as
+    // an expression.
+    let magnitude = num.abs();
+    if magnitude >= 1e21 || (magnitude < 1e-6 && magnitude != 0f64) {
+        let scientific = format!("{:e}", num);
+        let (mantissa, exponent) = scientific.split_at(scientific.find('e').unwrap());
+        let exponent = &exponent[1..];
+
+        return if exponent.starts_with('-') {
+            format!("{} * 10^({})", mantissa, exponent)
+        } else {
+            format!("{} * 10^{}", mantissa, exponent)
+        };
+    }
+
+    match decimal_digits(num) {
+        Some(digits) => format!("{:.*}", digits, num),
+        // The shortest representation that reads back as the same number
+        None => num.to_string(),
+    }
+}
+
+#[test]
+fn test_num_as_string() {
+    assert_eq!(num_as_string(42f64), "42");
+    assert_eq!(num_as_string(0.5f64), "0.5");
+}
+
+#[test]
+fn test_num_as_string_tiny_and_huge() {
+    assert_eq!(
+        num_as_string(1f64 / 2f64.powi(70)),
+        "8.470329472543003 * 10^(-22)"
+    );
+    assert_eq!(num_as_string(-2.5e-7), "-2.5 * 10^(-7)");
+    assert_eq!(num_as_string(1e300), "1 * 10^300");
+    assert_eq!(num_as_string(1e20), "100000000000000000000");
+    assert_eq!(num_as_string(0.000001), "0.000001");
+    assert_eq!(num_as_string(0.1 + 0.2), "0.30000000000000004");
+}
+
+#[test]
+fn test_num_as_string_not_finite() {
+    assert_eq!(num_as_string(f64::INFINITY), "inf");
+    assert_eq!(num_as_string(f64::NEG_INFINITY), "-inf");
+    assert_eq!(num_as_string(f64::NAN), "NaN");
+}
@@ -1,22 +1,35 @@
-use glib::signal::SignalHandlerId;
+use std::cell::RefCell;
+use std::f64;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use gdk::enums::key;
+use gdk::ModifierType;
 use gtk::prelude::*;
 use gtk::{
-    Builder, Button, CssProvider, Entry, EntryBuffer, Error, StyleContext, Window,
+    Application, Builder, Button, CssProvider, Entry, EntryIconPosition, Error, FileChooserAction,
+    FileChooserDialog, Label, ResponseType, SearchBar, SearchEntry, StyleContext, Window,
     STYLE_PROVIDER_PRIORITY_APPLICATION,
 };
+use pango::{AttrList, Attribute, Underline};
+
+use ralculator::{diagnose, parse_and_execute, Context, InputError, Value};
 
-use exec::parse_and_execute;
-use input_error::InputError;
-use text_range::TextRange;
+use args::Mode;
+use json::Failure;
 
 use super::history::History;
+use super::history_file::{self, Entry as HistoryEntry};
+use super::plot::PlotView;
+use super::preview::Preview;
 
 static LAYOUT_GLADE: &str = include_str!("layout.glade");
 static STYLE_CSS: &str = include_str!("style.css");
+static SHORTCUTS_UI: &str = include_str!("shortcuts.ui");
 
 type ButtoInfo = (&'static str, &'static str);
 
-static STRAIGHT_INPUT_BUTTONS: [ButtoInfo; 15] = [
+static STRAIGHT_INPUT_BUTTONS: [ButtoInfo; 19] = [
     ("num_1_button", "1"),
     ("num_2_button", "2"),
     ("num_3_button", "3"),
@@ -31,20 +44,74 @@ static STRAIGHT_INPUT_BUTTONS: [ButtoInfo; 15] = [
     ("add_button", " + "),
     ("subtract_button", " - "),
     ("multiply_button", " * "),
-    ("decimal_point_button", " . "),
+    ("decimal_point_button", "."),
+    ("sin_button", "sin("),
+    ("cos_button", "cos("),
+    ("tan_button", "tan("),
+    ("pi_button", "pi"),
+];
+
+/// Hidden in the programmer mode
+static SCIENTIFIC_BUTTONS: [&str; 5] = [
+    "angle_mode_button",
+    "sin_button",
+    "cos_button",
+    "tan_button",
+    "pi_button",
 ];
 
 pub struct App {
     builder: Builder,
     window: Window,
+    input: Entry,
+    history: History,
+    plot_view: PlotView,
+    preview: Preview,
+    /// The last result in other bases, only shown in the programmer mode
+    bases: Label,
+    context: Rc<RefCell<Context>>,
 }
 
 impl App {
-    pub fn new() -> App {
+    pub fn new(
+        application: &Application,
+        context: Rc<RefCell<Context>>,
+        history_file: Option<&PathBuf>,
+    ) -> App {
         let builder = Builder::new_from_string(LAYOUT_GLADE);
         let window: Window = builder.get_object("window").unwrap();
+        window.set_application(application);
+
+        let input: Entry = builder.get_object("input").unwrap();
+
+        let history = {
+            let list = builder.get_object("history").unwrap();
+            let history_scroll = builder.get_object("history_scroll").unwrap();
+            let file = history_file.cloned().or_else(history_file::default_path);
 
-        App { builder, window }
+            History::new(list, history_scroll, input.clone(), file)
+        };
+
+        let plot_view = {
+            let panel = builder.get_object("plot_panel").unwrap();
+            let area = builder.get_object("plot_area").unwrap();
+
+            PlotView::new(panel, area)
+        };
+
+        let preview = Preview::new(builder.get_object("preview").unwrap());
+        let bases = builder.get_object("bases").unwrap();
+
+        App {
+            builder,
+            window,
+            input,
+            history,
+            plot_view,
+            preview,
+            bases,
+            context,
+        }
     }
 
     pub fn init(&self) {
@@ -53,14 +120,45 @@ impl App {
         self.setup_inputs();
     }
 
-    pub fn setup_new() -> App {
-        let app = App::new();
+    pub fn setup_new(
+        application: &Application,
+        context: Rc<RefCell<Context>>,
+        history_file: Option<&PathBuf>,
+    ) -> App {
+        let app = App::new(application, context, history_file);
         app.init();
         app
     }
 
-    pub fn on_close<F: Fn() -> Inhibit + 'static>(&self, f: F) -> SignalHandlerId {
-        self.window.connect_delete_event(move |_, _| f())
+    pub fn present(&self) {
+        self.window.present();
+    }
+
+    pub fn set_mode(&self, mode: Mode) {
+        let programmer = mode == Mode::Programmer;
+
+        for id in &SCIENTIFIC_BUTTONS {
+            let button: Button = self.builder.get_object(id).unwrap();
+            button.set_visible(!programmer);
+        }
+        self.bases.set_visible(programmer);
+    }
+
+    pub fn clear_history(&self) {
+        self.history.clear();
+    }
+
+    /// Evaluates the expression as if it was entered by the user
+    pub fn evaluate(&self, expr: &str) {
+        self.input.set_text(expr);
+
+        handle_execute(
+            &self.input,
+            &self.history,
+            &self.plot_view,
+            &self.bases,
+            &self.context.borrow(),
+        );
     }
 
     fn add_css(&self) {
@@ -81,15 +179,11 @@ impl App {
     }
 
     fn setup_inputs(&self) {
-        let input: Entry = self.builder.get_object("input").unwrap();
+        let input = self.input.clone();
         input.set_alignment(1f32);
 
-        let history = {
-            let text_view = self.builder.get_object("history").unwrap();
-            let history_scroll = self.builder.get_object("history_scroll").unwrap();
-
-            History::new(text_view, history_scroll)
-        };
+        let history = self.history.clone();
+        let plot_view = self.plot_view.clone();
 
         for &(id, text) in &STRAIGHT_INPUT_BUTTONS {
             let input = input.clone();
@@ -98,12 +192,137 @@ impl App {
         }
 
         {
-            let input_buffer = input.get_buffer();
+            let context = self.context.clone();
+            let preview = self.preview.clone();
+
+            input.connect_changed(move |input| {
+                // The highlighted error doesn't apply to the changed text
+                input.set_attributes(&AttrList::new());
+                // Neither does a reverse search, which sets the icon after
+                // changing the text
+                input.set_icon_from_icon_name(EntryIconPosition::Secondary, None);
+
+                let text = input.get_buffer().get_text();
+                preview.update(&text, &context.borrow());
+            });
+        }
+
+        {
+            let context = self.context.clone();
+            let input = input.clone();
+            let preview = self.preview.clone();
+            let button: Button = self.builder.get_object("angle_mode_button").unwrap();
+            button.set_label(&context.borrow().angle_mode.to_string());
+
+            button.connect_clicked(move |button| {
+                let mut context = context.borrow_mut();
+                context.angle_mode = context.angle_mode.next();
+                button.set_label(&context.angle_mode.to_string());
+
+                // Results of trigonometric functions depend on the angle mode
+                let text = input.get_buffer().get_text();
+                preview.update(&text, &context);
+            });
+        }
+
+        {
+            let plot_view = plot_view.clone();
+            let window = self.window.clone();
+            let button: Button = self.builder.get_object("plot_export_button").unwrap();
+
+            button.connect_clicked(move |_| export_plot(&plot_view, &window));
+        }
+
+        {
+            let panel: gtk::Box = self.builder.get_object("plot_panel").unwrap();
+            let button: Button = self.builder.get_object("plot_close_button").unwrap();
+
+            button.connect_clicked(move |_| panel.hide());
+        }
+
+        {
+            let context = self.context.clone();
+            let bases = self.bases.clone();
             let button: Button = self.builder.get_object("calc_button").unwrap();
 
             button.connect_clicked(move |_| {
-                handle_execute(&input_buffer, &history);
+                // Focusing selects the text, so this comes before the cursor
+                // is placed at an error
                 input.grab_focus();
+                handle_execute(&input, &history, &plot_view, &bases, &context.borrow());
+            });
+        }
+
+        {
+            let button: Button = self.builder.get_object("calc_button").unwrap();
+            self.input.connect_activate(move |_| button.clicked());
+        }
+
+        {
+            let history = self.history.clone();
+
+            self.input.connect_key_press_event(move |input, event| {
+                let control = event.get_state().contains(ModifierType::CONTROL_MASK);
+
+                let text = match event.get_keyval() {
+                    key::r if control => {
+                        reverse_search(input, &history);
+                        return Inhibit(true);
+                    }
+                    key::Escape => Some(String::new()),
+                    key::Up => history.previous_input(),
+                    key::Down => history.next_input(),
+                    _ => return Inhibit(false),
+                };
+
+                if let Some(text) = text {
+                    input.set_text(&text);
+                    input.set_position(-1);
+                }
+                Inhibit(true)
+            });
+        }
+
+        {
+            let history = self.history.clone();
+            let search: SearchEntry = self.builder.get_object("history_search").unwrap();
+
+            search.connect_search_changed(move |search| {
+                history.filter(&search.get_buffer().get_text());
+            });
+        }
+
+        {
+            let search_bar: SearchBar = self.builder.get_object("history_search_bar").unwrap();
+            let search: SearchEntry = self.builder.get_object("history_search").unwrap();
+            let input = self.input.clone();
+            search_bar.connect_entry(&search);
+
+            // Closing the search bar shows all entries again
+            search_bar.connect_property_search_mode_enabled_notify(move |search_bar| {
+                if !search_bar.get_search_mode() {
+                    search.set_text("");
+                    input.grab_focus();
+                }
+            });
+        }
+
+        {
+            let history = self.history.clone();
+            let search_bar: SearchBar = self.builder.get_object("history_search_bar").unwrap();
+
+            self.window.connect_key_press_event(move |window, event| {
+                let control = event.get_state().contains(ModifierType::CONTROL_MASK);
+
+                match event.get_keyval() {
+                    key::f if control => search_bar.set_search_mode(!search_bar.get_search_mode()),
+                    key::l if control => history.clear(),
+                    key::s if control => history.export(),
+                    key::question if control => show_shortcuts(window),
+                    key::F1 => show_shortcuts(window),
+                    _ => return Inhibit(false),
+                }
+                Inhibit(true)
             });
         }
     }
@@ -126,43 +345,155 @@ fn insert_text(input: &Entry, text: &str) {
     input.set_position(pos + text.len() as i32);
 }
 
-fn handle_execute(input_buffer: &EntryBuffer, history: &History) {
-    let input = input_buffer.get_text();
+fn show_shortcuts(window: &Window) {
+    let builder = Builder::new_from_string(SHORTCUTS_UI);
+    let shortcuts: Window = builder.get_object("shortcuts").unwrap();
+    shortcuts.set_transient_for(Some(window));
+    shortcuts.show();
+}
+
+fn export_plot(plot_view: &PlotView, window: &Window) {
+    let dialog = FileChooserDialog::with_buttons(
+        "Export plot",
+        Some(window),
+        FileChooserAction::Save,
+        &[
+            ("_Cancel", ResponseType::Cancel),
+            ("_Save", ResponseType::Accept),
+        ],
+    );
+    dialog.set_current_name("plot.png");
+    dialog.set_do_overwrite_confirmation(true);
 
-    if input.is_empty() {
-        return;
+    if dialog.run() == ResponseType::Accept.into() {
+        if let Some(path) = dialog.get_filename() {
+            if let Err(err) = plot_view.export(&path) {
+                error!("Exporting the plot to {} failed: {}", path.display(), err);
+            }
+        }
     }
 
-    history.add(&input);
+    dialog.destroy();
+}
+
+/// Replaces the input with the newest earlier input containing it, again
+/// for older ones
+fn reverse_search(input: &Entry, history: &History) {
+    let text = input.get_buffer().get_text();
 
-    match parse_and_execute(&input) {
-        Ok(result) => {
-            let res_text = num_as_string(result);
-            input_buffer.set_text(&res_text);
-            history.add(&format!("= {}", &res_text));
+    match history.reverse_search(&text) {
+        Some(found) => {
+            input.set_text(&found);
+            input.set_position(-1);
+            input.set_icon_from_icon_name(EntryIconPosition::Secondary, "edit-find-symbolic");
+            input.set_icon_tooltip_text(
+                EntryIconPosition::Secondary,
+                "Press Ctrl+R again for older inputs",
+            );
         }
-        Err(InputError { msg: _msg, pos }) => {
-            input_buffer.set_text("Error");
+        None => input.error_bell(),
+    }
+}
+
+/// Underlines the erroneous parts of the input and moves the cursor to the
+/// first one
+fn highlight_errors(input: &Entry, errors: &[InputError]) {
+    let attributes = AttrList::new();
 
-            let marker = pos.fill('^');
-            let pad = TextRange::new(pos.end, input.len()).fill(' ');
+    for err in errors {
+        let underline = Attribute::new_underline(Underline::Error);
+        let color = Attribute::new_underline_color(0xc0c0, 0x1c1c, 0x2828);
 
-            // TODO: fix messages
-            // history.add(&format!("{} {}{}", msg, marker, pad));
-            history.add(&format!("{}{}", marker, pad));
+        for mut attribute in underline.into_iter().chain(color) {
+            // Pango indices are byte offsets
+            attribute.set_start_index(err.pos.start.offset as u32);
+            attribute.set_end_index(err.pos.end.offset as u32);
+            attributes.insert(attribute);
         }
     }
+
+    input.set_attributes(&attributes);
+    if let Some(err) = errors.first() {
+        input.set_position(err.pos.start.index as i32);
+    }
+}
+
+/// Formats an integer in hexadecimal, octal and binary, negative numbers in
+/// two's complement
+fn format_bases(n: f64) -> Option<String> {
+    // Larger numbers aren't exact anymore
+    const MAX_EXACT: f64 = 9_007_199_254_740_992f64;
+
+    if n.fract() != 0f64 || n.abs() > MAX_EXACT {
+        return None;
+    }
+
+    let n = n as i64;
+    Some(format!("{:#x}  {:#o}  {:#b}", n, n, n))
+}
+
+#[test]
+fn test_format_bases() {
+    assert_eq!(format_bases(10f64), Some(String::from("0xa  0o12  0b1010")));
+    assert_eq!(
+        format_bases(-1f64),
+        Some(format!(
+            "0x{}  0o1{}  0b{}",
+            "f".repeat(16),
+            "7".repeat(21),
+            "1".repeat(64)
+        ))
+    );
+    assert_eq!(format_bases(1.5), None);
+    assert_eq!(format_bases(1e20), None);
+    assert_eq!(format_bases(f64::INFINITY), None);
 }
 
-fn num_as_string(num: f64) -> String {
-    fn decimal_digits(num: f64) -> usize {
-        for n in 0..20usize {
-            if (num * (10usize.pow(n as u32)) as f64) % 1f64 == 0f64 {
-                return n;
+fn handle_execute(
+    input: &Entry,
+    history: &History,
+    plot_view: &PlotView,
+    bases: &Label,
+    context: &Context,
+) {
+    let input_buffer = input.get_buffer();
+    let text = input_buffer.get_text();
+
+    if text.is_empty() {
+        return;
+    }
+
+    let result = match parse_and_execute(&text, context) {
+        Ok(result) => {
+            let res_text = result.to_string();
+
+            let in_bases = match result {
+                Value::Number(n) => format_bases(n),
+                _ => None,
+            };
+            bases.set_text(&in_bases.unwrap_or_default());
+
+            // Only plain numbers can be used in the next input, e.g. a list
+            // of solutions or an error estimate can't. The input stays so it
+            // can be changed, e.g. to adjust a plot.
+            match result {
+                Value::Number(_) | Value::Exact(_) | Value::Angle(..) => {
+                    input_buffer.set_text(&res_text)
+                }
+                Value::Plot(plot) => plot_view.show(plot, context),
+                Value::Solutions(..) | Value::Estimate(..) | Value::Expression(_) => {}
             }
+
+            Ok(res_text)
         }
-        panic!("Could not determine decimal digits");
-    }
+        Err(_) => {
+            bases.set_text("");
+            // The input stays in the entry so it can be corrected
+            let errors = diagnose(&text, context);
+            highlight_errors(input, &errors);
+            Err(errors.into_iter().map(Failure::new).collect())
+        }
+    };
 
-    format!("{:.*}", decimal_digits(num), num)
+    history.add(HistoryEntry::new(&text, result));
 }
@@ -0,0 +1,362 @@
+//! Exporting objects on a D-Bus connection, gio only has bindings for the
+//! client side
+
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_uint};
+use std::ptr;
+use std::rc::Rc;
+
+use gio_sys::{
+    g_dbus_connection_register_object, g_dbus_connection_unregister_object,
+    g_dbus_method_invocation_return_dbus_error, g_dbus_method_invocation_return_value,
+    g_dbus_node_info_lookup_interface, g_dbus_node_info_new_for_xml, g_dbus_node_info_unref,
+    GDBusConnection, GDBusInterfaceVTable, GDBusMethodInvocation,
+};
+use glib_sys::{
+    g_error_free, g_variant_builder_add_value, g_variant_builder_end, g_variant_builder_init,
+    g_variant_get_child_value, g_variant_get_string, g_variant_n_children,
+    g_variant_new_dict_entry, g_variant_new_string, g_variant_new_strv, g_variant_new_tuple,
+    g_variant_unref, gpointer, GError, GVariant, GVariantBuilder, GVariantType,
+};
+
+/// Why a method call failed, the caller receives it as D-Bus error
+#[derive(Debug, PartialEq)]
+pub enum MethodError {
+    UnknownMethod,
+    /// The arguments have the right type but can't be used, e.g. an
+    /// expression with a syntax error
+    InvalidArgs(String),
+}
+
+/// An interface that can be exported
+pub trait Interface {
+    /// The name of the interface
+    fn name(&self) -> &'static str;
+
+    /// The introspection data, a `<node>` with the interface
+    fn xml(&self) -> &'static str;
+
+    /// Returns the reply to a method call. The parameters were already
+    /// checked against the introspection data.
+    ///
+    /// # Safety
+    ///
+    /// `parameters` has to be a valid tuple variant, the reply is a new
+    /// floating reference.
+    unsafe fn call(
+        &self,
+        method: &str,
+        parameters: *mut GVariant,
+    ) -> Result<*mut GVariant, MethodError>;
+}
+
+/// An exported interface, it is unregistered when this is dropped
+pub struct Export {
+    connection: *mut GDBusConnection,
+    registration_id: c_uint,
+}
+
+impl Export {
+    /// Exports the interface at `object_path` on the connection
+    ///
+    /// # Safety
+    ///
+    /// `connection` has to be a valid D-Bus connection that outlives the
+    /// export. Calls are dispatched in the main context of this thread.
+    pub unsafe fn register(
+        connection: *mut GDBusConnection,
+        object_path: &str,
+        interface: Rc<dyn Interface>,
+    ) -> Result<Export, String> {
+        let object_path = CString::new(object_path).map_err(|err| err.to_string())?;
+        let interface_name = CString::new(interface.name()).unwrap();
+        let xml = CString::new(interface.xml()).unwrap();
+        let mut error: *mut GError = ptr::null_mut();
+
+        let node_info = g_dbus_node_info_new_for_xml(xml.as_ptr(), &mut error);
+        if node_info.is_null() {
+            return Err(take_error(error));
+        }
+
+        let vtable = GDBusInterfaceVTable {
+            method_call: Some(handle_method_call),
+            get_property: None,
+            set_property: None,
+            padding: [ptr::null_mut(); 8],
+        };
+        let user_data = Box::into_raw(Box::new(interface));
+
+        // The interface info and the vtable are copied
+        let registration_id = g_dbus_connection_register_object(
+            connection,
+            object_path.as_ptr(),
+            g_dbus_node_info_lookup_interface(node_info, interface_name.as_ptr()),
+            &vtable,
+            user_data as gpointer,
+            Some(free_interface),
+            &mut error,
+        );
+        g_dbus_node_info_unref(node_info);
+
+        if registration_id == 0 {
+            return Err(take_error(error));
+        }
+
+        Ok(Export {
+            connection,
+            registration_id,
+        })
+    }
+}
+
+impl Drop for Export {
+    fn drop(&mut self) {
+        unsafe {
+            g_dbus_connection_unregister_object(self.connection, self.registration_id);
+        }
+    }
+}
+
+unsafe fn take_error(error: *mut GError) -> String {
+    if error.is_null() {
+        return String::from("unknown error");
+    }
+
+    let msg = CStr::from_ptr((*error).message)
+        .to_string_lossy()
+        .into_owned();
+    g_error_free(error);
+    msg
+}
+
+unsafe extern "C" fn free_interface(user_data: gpointer) {
+    drop(Box::from_raw(user_data as *mut Rc<dyn Interface>));
+}
+
+unsafe extern "C" fn handle_method_call(
+    _connection: *mut GDBusConnection,
+    _sender: *const c_char,
+    _object_path: *const c_char,
+    _interface_name: *const c_char,
+    method_name: *const c_char,
+    parameters: *mut GVariant,
+    invocation: *mut GDBusMethodInvocation,
+    user_data: gpointer,
+) {
+    let interface = &*(user_data as *const Rc<dyn Interface>);
+    let method = CStr::from_ptr(method_name).to_string_lossy();
+
+    let (name, message) = match interface.call(&method, parameters) {
+        Ok(reply) => return g_dbus_method_invocation_return_value(invocation, reply),
+        Err(MethodError::UnknownMethod) => ("UnknownMethod", String::from("Unknown method")),
+        Err(MethodError::InvalidArgs(message)) => ("InvalidArgs", message),
+    };
+
+    let name = CString::new(format!("org.freedesktop.DBus.Error.{}", name)).unwrap();
+    let message = CString::new(message.replace('\0', "")).unwrap();
+    g_dbus_method_invocation_return_dbus_error(invocation, name.as_ptr(), message.as_ptr());
+}
+
+/// The string at `index` of a tuple
+pub unsafe fn get_string(tuple: *mut GVariant, index: usize) -> String {
+    let child = g_variant_get_child_value(tuple, index);
+    let s = CStr::from_ptr(g_variant_get_string(child, ptr::null_mut()))
+        .to_string_lossy()
+        .into_owned();
+    g_variant_unref(child);
+    s
+}
+
+/// The string array at `index` of a tuple
+pub unsafe fn get_strings(tuple: *mut GVariant, index: usize) -> Vec<String> {
+    let array = g_variant_get_child_value(tuple, index);
+    let strings = (0..g_variant_n_children(array))
+        .map(|i| get_string(array, i))
+        .collect();
+    g_variant_unref(array);
+    strings
+}
+
+pub unsafe fn new_string(s: &str) -> *mut GVariant {
+    // D-Bus strings can't contain NUL characters
+    let s = CString::new(s.replace('\0', "")).unwrap();
+    g_variant_new_string(s.as_ptr())
+}
+
+pub unsafe fn new_strings(strings: &[String]) -> *mut GVariant {
+    let strings: Vec<CString> = strings
+        .iter()
+        .map(|s| CString::new(s.replace('\0', "")).unwrap())
+        .collect();
+    let pointers: Vec<*const c_char> = strings.iter().map(|s| s.as_ptr()).collect();
+    g_variant_new_strv(pointers.as_ptr(), pointers.len() as isize)
+}
+
+pub unsafe fn new_tuple(children: &[*mut GVariant]) -> *mut GVariant {
+    g_variant_new_tuple(children.as_ptr(), children.len())
+}
+
+/// A dictionary of the given type, e.g. `a{sd}`, with string keys
+pub unsafe fn new_dict(type_string: &str, entries: Vec<(String, *mut GVariant)>) -> *mut GVariant {
+    let type_string = CString::new(type_string).unwrap();
+    let mut builder: GVariantBuilder = ::std::mem::zeroed();
+    g_variant_builder_init(&mut builder, type_string.as_ptr() as *const GVariantType);
+
+    for (key, value) in entries {
+        g_variant_builder_add_value(
+            &mut builder,
+            g_variant_new_dict_entry(new_string(&key), value),
+        );
+    }
+    g_variant_builder_end(&mut builder)
+}
+
+/// An array of the given type, e.g. `aa{sv}`
+pub unsafe fn new_array(type_string: &str, items: Vec<*mut GVariant>) -> *mut GVariant {
+    let type_string = CString::new(type_string).unwrap();
+    let mut builder: GVariantBuilder = ::std::mem::zeroed();
+    g_variant_builder_init(&mut builder, type_string.as_ptr() as *const GVariantType);
+
+    for item in items {
+        g_variant_builder_add_value(&mut builder, item);
+    }
+    g_variant_builder_end(&mut builder)
+}
+
+/// Calls the method without a bus, the arguments and the reply are in the
+/// GVariant text format like `gdbus` uses them
+#[cfg(test)]
+pub fn call_directly(
+    interface: &dyn Interface,
+    method: &str,
+    args: &str,
+) -> Result<String, MethodError> {
+    use glib_sys::{g_free, g_variant_parse, g_variant_print, g_variant_ref_sink, GTRUE};
+
+    unsafe {
+        let text = CString::new(args).unwrap();
+        let mut error = ptr::null_mut();
+        let parameters = g_variant_parse(
+            ptr::null(),
+            text.as_ptr(),
+            ptr::null(),
+            ptr::null_mut(),
+            &mut error,
+        );
+        assert!(!parameters.is_null(), "{}", take_error(error));
+
+        let reply = interface.call(method, parameters);
+        g_variant_unref(parameters);
+
+        reply.map(|reply| {
+            let reply = g_variant_ref_sink(reply);
+            let printed = g_variant_print(reply, GTRUE);
+            let text = CStr::from_ptr(printed).to_string_lossy().into_owned();
+            g_free(printed as gpointer);
+            g_variant_unref(reply);
+            text
+        })
+    }
+}
+
+/// A private message bus, killed when this is dropped
+#[cfg(test)]
+pub struct TestBus {
+    daemon: ::std::process::Child,
+    /// Tests run in parallel, so each bus dispatches calls in its own context
+    context: *mut glib_sys::GMainContext,
+    pub address: String,
+    pub connection: *mut GDBusConnection,
+    /// The unique name of `connection`
+    pub name: String,
+}
+
+#[cfg(test)]
+impl TestBus {
+    /// Starts a private bus, panics if `dbus-daemon` isn't installed
+    pub fn start() -> TestBus {
+        use std::io::{BufRead, BufReader};
+        use std::process::{Command, Stdio};
+
+        use gio_sys::{
+            g_dbus_connection_get_unique_name, g_dbus_connection_new_for_address_sync,
+            G_DBUS_CONNECTION_FLAGS_AUTHENTICATION_CLIENT,
+            G_DBUS_CONNECTION_FLAGS_MESSAGE_BUS_CONNECTION,
+        };
+
+        let mut daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("dbus-daemon not found");
+
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.as_mut().unwrap())
+            .read_line(&mut address)
+            .unwrap();
+        let address = address.trim().to_string();
+
+        unsafe {
+            let context = glib_sys::g_main_context_new();
+            glib_sys::g_main_context_push_thread_default(context);
+
+            let mut error = ptr::null_mut();
+            let c_address = CString::new(address.as_str()).unwrap();
+            let connection = g_dbus_connection_new_for_address_sync(
+                c_address.as_ptr(),
+                G_DBUS_CONNECTION_FLAGS_AUTHENTICATION_CLIENT
+                    | G_DBUS_CONNECTION_FLAGS_MESSAGE_BUS_CONNECTION,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                &mut error,
+            );
+            assert!(!connection.is_null(), "{}", take_error(error));
+
+            let name = CStr::from_ptr(g_dbus_connection_get_unique_name(connection))
+                .to_string_lossy()
+                .into_owned();
+
+            TestBus {
+                daemon,
+                context,
+                address,
+                connection,
+                name,
+            }
+        }
+    }
+
+    /// Calls a method of the object at `/test` with `gdbus`, the arguments
+    /// are in the GVariant text format
+    pub fn call(&self, method: &str, args: &[&str]) -> String {
+        use std::process::{Command, Stdio};
+
+        let mut gdbus = Command::new("gdbus")
+            .args(["call", "--address", &self.address, "--dest", &self.name])
+            .args(["--object-path", "/test", "--method", method])
+            .args(args)
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("gdbus not found");
+
+        // The main context has to be iterated for the exported objects to answer
+        while gdbus.try_wait().unwrap().is_none() {
+            unsafe { glib_sys::g_main_context_iteration(self.context, glib_sys::GFALSE) };
+            ::std::thread::sleep(::std::time::Duration::from_millis(5));
+        }
+
+        let output = gdbus.wait_with_output().unwrap();
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestBus {
+    fn drop(&mut self) {
+        unsafe {
+            glib_sys::g_main_context_pop_thread_default(self.context);
+            glib_sys::g_main_context_unref(self.context);
+        }
+        self.daemon.kill().unwrap();
+    }
+}
@@ -1,41 +1,490 @@
+use std::cell::{Cell, RefCell};
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use gdk;
 use gtk::prelude::*;
-use gtk::{ScrolledWindow, TextBuffer, TextView};
+use gtk::{
+    Align, Button, ButtonsType, CheckButton, Clipboard, DialogFlags, FileChooserAction,
+    FileChooserDialog, Label, ListBox, ListBoxRow, Menu, MenuItem, MessageDialog, MessageType,
+    Orientation, ReliefStyle, ResponseType, ScrolledWindow, SeparatorMenuItem, Window,
+};
+
+use ralculator::{display_width, TextRange};
+
+use json::Failure;
+
+use super::history_export;
+use super::history_file::{self, contains_ignoring_case, Entry, MAX_ENTRIES};
+
+/// Number of entries appended beyond `MAX_ENTRIES` before the history file is
+/// trimmed, so it isn't rewritten on every evaluation once it is full
+const TRIM_SLACK: usize = 100;
+
+/// The state of searching older inputs from the input, like Ctrl+R in shells
+struct ReverseSearch {
+    query: String,
+    /// The index of the last match
+    index: usize,
+    /// The input of the last match, searching again while it is in the input
+    /// continues with older matches
+    found: String,
+}
 
+/// The evaluated inputs, one row per entry. Clicking the input or result of
+/// an entry inserts it into the input, right-clicking opens a menu.
 #[derive(Clone)]
 pub struct History {
-    text_view: TextView,
-    buffer: TextBuffer,
+    list: ListBox,
     history_scroll: ScrolledWindow,
+    input: ::gtk::Entry,
+    /// Entries are read from and appended to this file
+    file: Option<PathBuf>,
+    /// Evaluated inputs of this and earlier sessions, oldest first, in the
+    /// order of the rows
+    entries: Rc<RefCell<Vec<Entry>>>,
+    /// Number of entries at the start of `entries` that are from earlier
+    /// sessions
+    earlier: Rc<Cell<usize>>,
+    /// The input shown while walking through `entries` with the arrow keys
+    cursor: Rc<Cell<Option<usize>>>,
+    /// Set when a row was added, the view scrolls to it once its size is
+    /// known
+    scroll_to_end: Rc<Cell<bool>>,
+    /// Only entries whose input or result contains this are shown
+    filter: Rc<RefCell<String>>,
+    reverse_search: Rc<RefCell<Option<ReverseSearch>>>,
 }
 
 impl History {
-    pub fn new(text_view: TextView, history_scroll: ScrolledWindow) -> History {
-        let buffer = text_view.get_buffer().unwrap();
-        History {
-            text_view,
-            buffer,
+    pub fn new(
+        list: ListBox,
+        history_scroll: ScrolledWindow,
+        input: ::gtk::Entry,
+        file: Option<PathBuf>,
+    ) -> History {
+        let history = History {
+            list,
             history_scroll,
+            input,
+            file,
+            entries: Rc::new(RefCell::new(Vec::new())),
+            earlier: Rc::new(Cell::new(0)),
+            cursor: Rc::new(Cell::new(None)),
+            scroll_to_end: Rc::new(Cell::new(false)),
+            filter: Rc::new(RefCell::new(String::new())),
+            reverse_search: Rc::new(RefCell::new(None)),
+        };
+
+        {
+            let entries = history.entries.clone();
+            let filter = history.filter.clone();
+
+            history.list.set_filter_func(Some(Box::new(move |row| {
+                let filter = filter.borrow();
+                let entries = entries.borrow();
+
+                match entries.get(row.get_index() as usize) {
+                    Some(entry) => entry.matches(&filter),
+                    None => true,
+                }
+            })));
+        }
+
+        // Scrolling right after adding a row would scroll to the old end
+        {
+            let scroll_to_end = history.scroll_to_end.clone();
+            let adjustment = history.history_scroll.get_vadjustment().unwrap();
+
+            adjustment.connect_changed(move |adjustment| {
+                if scroll_to_end.replace(false) {
+                    adjustment.set_value(adjustment.get_upper() - adjustment.get_page_size());
+                }
+            });
+        }
+
+        if let Some(ref path) = history.file {
+            match history_file::load(path) {
+                Ok(entries) => {
+                    history.earlier.set(entries.len());
+                    *history.entries.borrow_mut() = entries;
+                    for entry in history.entries.borrow().iter() {
+                        history.add_row(entry);
+                    }
+                    history.trim();
+                }
+                Err(err) => error!("Reading history {} failed: {}", path.display(), err),
+            }
+        }
+
+        history
+    }
+
+    /// Adds an evaluated input, which can be recalled with `previous_input`
+    pub fn add(&self, entry: Entry) {
+        // The filter looks up the entry of the new row
+        self.entries.borrow_mut().push(entry.clone());
+        self.add_row(&entry);
+        self.cursor.set(None);
+
+        if let Some(ref path) = self.file {
+            if let Err(err) = history_file::append(path, &entry) {
+                error!("Writing history {} failed: {}", path.display(), err);
+            }
+        }
+
+        self.trim();
+    }
+
+    /// Drops the oldest entries once there are `TRIM_SLACK` more than the
+    /// history file keeps
+    fn trim(&self) {
+        let mut entries = self.entries.borrow_mut();
+        if entries.len() <= MAX_ENTRIES + TRIM_SLACK {
+            return;
+        }
+
+        let excess = entries.len() - MAX_ENTRIES;
+        entries.drain(..excess);
+        self.earlier.set(self.earlier.get().saturating_sub(excess));
+        self.reverse_search.replace(None);
+        for row in self.list.get_children().into_iter().take(excess) {
+            self.list.remove(&row);
+        }
+
+        self.save(&entries);
+    }
+
+    /// The input before the one that was recalled last, `None` if there is
+    /// no older one
+    pub fn previous_input(&self) -> Option<String> {
+        let entries = self.entries.borrow();
+        let index = match self.cursor.get() {
+            Some(0) => return None,
+            Some(index) => index - 1,
+            None => entries.len().checked_sub(1)?,
+        };
+
+        self.cursor.set(Some(index));
+        Some(entries[index].input.clone())
+    }
+
+    /// The input after the one that was recalled last, an empty input after
+    /// the newest one
+    pub fn next_input(&self) -> Option<String> {
+        let index = self.cursor.get()? + 1;
+        let entries = self.entries.borrow();
+
+        if index < entries.len() {
+            self.cursor.set(Some(index));
+            Some(entries[index].input.clone())
+        } else {
+            self.cursor.set(None);
+            Some(String::new())
+        }
+    }
+
+    /// The newest input containing `text`, ignoring case. Searching again
+    /// while the match is the input continues with older inputs like Ctrl+R
+    /// in shells. `None` if there is no (older) match.
+    pub fn reverse_search(&self, text: &str) -> Option<String> {
+        let mut reverse_search = self.reverse_search.borrow_mut();
+        let (query, before) = match *reverse_search {
+            Some(ref search) if search.found == text => (search.query.clone(), search.index),
+            _ => (text.to_string(), self.entries.borrow().len()),
+        };
+
+        let entries = self.entries.borrow();
+        let (index, entry) = entries[..before]
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|&(_, entry)| contains_ignoring_case(&entry.input, &query))?;
+
+        // The arrow keys continue from the match
+        self.cursor.set(Some(index));
+        *reverse_search = Some(ReverseSearch {
+            query,
+            index,
+            found: entry.input.clone(),
+        });
+        Some(entry.input.clone())
+    }
+
+    /// Only shows entries whose input or result contains `query`, ignoring
+    /// case
+    pub fn filter(&self, query: &str) {
+        *self.filter.borrow_mut() = query.to_string();
+        self.list.invalidate_filter();
+        self.scroll_to_end.set(true);
+    }
+
+    /// Asks for a file and writes the shown entries to it, in the format of
+    /// its extension. By default only the entries of this session are
+    /// written.
+    pub fn export(&self) {
+        let window = self.window();
+        let dialog = FileChooserDialog::with_buttons(
+            "Export history",
+            window.as_ref(),
+            FileChooserAction::Save,
+            &[
+                ("_Cancel", ResponseType::Cancel),
+                ("_Save", ResponseType::Accept),
+            ],
+        );
+        dialog.set_current_name("history.txt");
+        dialog.set_do_overwrite_confirmation(true);
+
+        let this_session = CheckButton::new_with_label("Only entries of this session");
+        this_session.set_active(true);
+        dialog.set_extra_widget(&this_session);
+
+        if dialog.run() == ResponseType::Accept.into() {
+            if let Some(path) = dialog.get_filename() {
+                let entries = self.shown_entries(this_session.get_active());
+                if let Err(err) = history_export::export(&entries, &path) {
+                    error!(
+                        "Exporting the history to {} failed: {}",
+                        path.display(),
+                        err
+                    );
+                }
+            }
+        }
+
+        dialog.destroy();
+    }
+
+    /// The entries that match the filter, oldest first, optionally without
+    /// those of earlier sessions
+    fn shown_entries(&self, this_session: bool) -> Vec<Entry> {
+        let filter = self.filter.borrow();
+        let skip = if this_session { self.earlier.get() } else { 0 };
+
+        self.entries
+            .borrow()
+            .iter()
+            .skip(skip)
+            .filter(|entry| entry.matches(&filter))
+            .cloned()
+            .collect()
+    }
+
+    fn window(&self) -> Option<Window> {
+        self.list
+            .get_toplevel()
+            .and_then(|widget| widget.downcast::<Window>().ok())
+    }
+
+    /// Asks for confirmation, then removes all entries, including the ones in
+    /// the history file
+    pub fn clear(&self) {
+        let dialog = MessageDialog::new(
+            self.window().as_ref(),
+            DialogFlags::MODAL,
+            MessageType::Question,
+            ButtonsType::None,
+            "Clear the history?",
+        );
+        dialog.set_property_secondary_text("The entries of earlier sessions are deleted as well.");
+        dialog.add_button("_Cancel", ResponseType::Cancel);
+        let clear = dialog.add_button("_Clear", ResponseType::Accept);
+        clear.get_style_context().add_class("destructive-action");
+        dialog.set_default_response(ResponseType::Cancel);
+
+        let confirmed = dialog.run() == ResponseType::Accept.into();
+        dialog.destroy();
+
+        if confirmed {
+            self.remove_all();
         }
     }
 
-    pub fn add(&self, text: &str) {
-        if self.is_empty() {
-            self.insert("\n");
+    fn remove_all(&self) {
+        for row in self.list.get_children() {
+            self.list.remove(&row);
+        }
+        self.entries.borrow_mut().clear();
+        self.earlier.set(0);
+        self.cursor.set(None);
+        self.reverse_search.replace(None);
+        self.save(&[]);
+    }
+
+    /// Removes the entry of the row, also from the history file
+    fn delete(&self, row: &ListBoxRow) {
+        let index = row.get_index();
+        if index < 0 {
+            return;
+        }
+
+        let mut entries = self.entries.borrow_mut();
+        entries.remove(index as usize);
+        if (index as usize) < self.earlier.get() {
+            self.earlier.set(self.earlier.get() - 1);
+        }
+        self.list.remove(row);
+        self.cursor.set(None);
+        self.reverse_search.replace(None);
+        self.save(&entries);
+    }
+
+    fn save(&self, entries: &[Entry]) {
+        if let Some(ref path) = self.file {
+            if let Err(err) = history_file::save(path, entries) {
+                error!("Writing history {} failed: {}", path.display(), err);
+            }
+        }
+    }
+
+    /// Shows the input and its result or errors in a new row at the end
+    fn add_row(&self, entry: &Entry) {
+        let row = ListBoxRow::new();
+        row.set_activatable(false);
+
+        let lines = ::gtk::Box::new(Orientation::Vertical, 0);
+        lines.add(&self.insert_button(&entry.input, &entry.input));
+
+        match entry.result {
+            Ok(ref result) => lines.add(&self.insert_button(&format!("= {}", result), result)),
+            Err(ref failures) => {
+                for failure in failures {
+                    let label = Label::new(error_line(&entry.input, failure).as_str());
+                    label.set_halign(Align::End);
+                    label.get_style_context().add_class("history-error");
+                    lines.add(&label);
+                }
+            }
         }
 
-        self.insert(text);
+        let menu = self.context_menu(&row, entry);
+        row.connect_button_press_event(move |_, event| {
+            if event.get_button() != 3 {
+                return Inhibit(false);
+            }
 
-        // FIXME: New content is added two lines under the bottom
-        let va = self.history_scroll.get_vadjustment().unwrap();
-        va.set_value(va.get_upper() + 15f64);
+            menu.popup_easy(event.get_button(), event.get_time());
+            Inhibit(true)
+        });
+
+        row.add(&lines);
+        row.show_all();
+        self.list.add(&row);
+        self.scroll_to_end.set(true);
     }
 
-    fn insert(&self, text: &str) {
-        self.buffer.insert(&mut self.buffer.get_end_iter(), text);
+    /// A button showing `label`, clicking it inserts `text` at the cursor of
+    /// the input
+    fn insert_button(&self, label: &str, text: &str) -> Button {
+        let button = Button::new_with_label(label);
+        button.set_relief(ReliefStyle::None);
+        button.set_halign(Align::End);
+
+        let input = self.input.clone();
+        let text = text.to_string();
+        button.connect_clicked(move |_| {
+            // Focusing selects the text and moves the cursor to its end
+            let mut position = input.get_position();
+            input.grab_focus();
+            input.insert_text(&text, &mut position);
+            input.set_position(position);
+        });
+
+        button
     }
 
-    pub fn is_empty(&self) -> bool {
-        let (start, end) = self.buffer.get_bounds();
-        start != end
+    /// Copy and delete for the entry of the row
+    fn context_menu(&self, row: &ListBoxRow, entry: &Entry) -> Menu {
+        let menu = Menu::new();
+
+        menu.append(&copy_menu_item("Copy Input", &entry.input));
+        if let Ok(ref result) = entry.result {
+            menu.append(&copy_menu_item("Copy Result", result));
+        }
+
+        let delete = MenuItem::new_with_label("Delete");
+        {
+            let history = self.clone();
+            // The menu belongs to the row, so it must not keep the row alive
+            let row = row.downgrade();
+
+            delete.connect_activate(move |_| {
+                if let Some(row) = row.upgrade() {
+                    history.delete(&row);
+                }
+            });
+        }
+        menu.append(&delete);
+
+        let export = MenuItem::new_with_label("Export History…");
+        {
+            let history = self.clone();
+            export.connect_activate(move |_| history.export());
+        }
+
+        let clear = MenuItem::new_with_label("Clear History");
+        {
+            let history = self.clone();
+            clear.connect_activate(move |_| history.clear());
+        }
+        menu.append(&SeparatorMenuItem::new());
+        menu.append(&export);
+        menu.append(&clear);
+
+        menu.set_property_attach_widget(Some(row));
+        menu.show_all();
+        menu
     }
 }
+
+fn copy_menu_item(label: &str, text: &str) -> MenuItem {
+    let item = MenuItem::new_with_label(label);
+    let text = text.to_string();
+
+    item.connect_activate(move |_| {
+        Clipboard::get(&gdk::SELECTION_CLIPBOARD).set_text(&text);
+    });
+
+    item
+}
+
+/// The message followed by carets, which are under the erroneous part of the
+/// input if the input is directly above
+fn error_line(input: &str, failure: &Failure) -> String {
+    // Failures count characters
+    let offset = |index| {
+        input
+            .char_indices()
+            .nth(index)
+            .map(|(offset, _)| offset)
+            .unwrap_or(input.len())
+    };
+    let range = TextRange::new(input, offset(failure.start), offset(failure.end));
+    let pad = " ".repeat(display_width(&input[range.end.offset..]));
+
+    format!("{} {}{}", failure.message, range.marker(input), pad)
+}
+
+#[test]
+fn test_error_line() {
+    let failure = Failure {
+        message: String::from("unknown identifier foo"),
+        start: 4,
+        end: 7,
+    };
+    assert_eq!(
+        error_line("π × foo + 1", &failure),
+        "unknown identifier foo ^^^    "
+    );
+
+    let failure = Failure {
+        message: String::from("expected (, identifier or number"),
+        start: 3,
+        end: 3,
+    };
+    assert_eq!(
+        error_line("1 +", &failure),
+        "expected (, identifier or number ^"
+    );
+}
@@ -0,0 +1,321 @@
+//! Exports the history as plain text, CSV, Markdown or a PDF paper tape
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use cairo;
+use cairo::prelude::*;
+use cairo::{FontSlant, FontWeight};
+
+use json::Failure;
+
+use super::history_file::Entry;
+
+/// A4 in points
+const PAGE_SIZE: (f64, f64) = (595f64, 842f64);
+const PAGE_MARGIN: f64 = 48f64;
+const FONT_SIZE: f64 = 10f64;
+const LINE_HEIGHT: f64 = 14f64;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Format {
+    Text,
+    Csv,
+    Markdown,
+    Pdf,
+}
+
+impl Format {
+    /// The format for the extension of the file name, plain text for
+    /// unknown ones
+    pub fn from_path(path: &Path) -> Format {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_lowercase());
+
+        match extension.as_deref() {
+            Some("csv") => Format::Csv,
+            Some("md") | Some("markdown") => Format::Markdown,
+            Some("pdf") => Format::Pdf,
+            _ => Format::Text,
+        }
+    }
+}
+
+/// Writes the entries to a file in the format of its extension
+pub fn export(entries: &[Entry], path: &Path) -> Result<(), String> {
+    let text = match Format::from_path(path) {
+        Format::Text => to_text(entries),
+        Format::Csv => to_csv(entries),
+        Format::Markdown => to_markdown(entries),
+        Format::Pdf => return write_pdf(entries, path),
+    };
+
+    File::create(path)
+        .and_then(|mut file| file.write_all(text.as_bytes()))
+        .map_err(|err| err.to_string())
+}
+
+/// The time, the input and the result or errors of every entry
+fn to_text(entries: &[Entry]) -> String {
+    let blocks: Vec<String> = entries
+        .iter()
+        .map(|entry| {
+            let mut lines = vec![format_timestamp(entry.timestamp), entry.input.clone()];
+            lines.extend(result_lines(entry));
+            lines.join("\n") + "\n"
+        })
+        .collect();
+
+    blocks.join("\n")
+}
+
+/// One row with the columns time, input, result and error per entry, as in
+/// RFC 4180
+fn to_csv(entries: &[Entry]) -> String {
+    let mut csv = String::from("time,input,result,error\r\n");
+
+    for entry in entries {
+        let (result, error) = match entry.result {
+            Ok(ref result) => (result.clone(), String::new()),
+            Err(ref failures) => (String::new(), error_messages(failures)),
+        };
+
+        let fields = [
+            format_timestamp(entry.timestamp),
+            entry.input.clone(),
+            result,
+            error,
+        ];
+        let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        csv.push_str(&fields.join(","));
+        csv.push_str("\r\n");
+    }
+
+    csv
+}
+
+/// A table with the columns time, input and result
+fn to_markdown(entries: &[Entry]) -> String {
+    let mut markdown = String::from("| Time | Input | Result |\n| --- | --- | --- |\n");
+
+    for entry in entries {
+        let result = match entry.result {
+            Ok(ref result) => code_span(result),
+            Err(ref failures) => format!("**Error:** {}", error_messages(failures)),
+        };
+
+        // Pipes end cells, even in code spans
+        markdown.push_str(&format!(
+            "| {} | {} | {} |\n",
+            format_timestamp(entry.timestamp),
+            code_span(&entry.input).replace('|', "\\|"),
+            result.replace('|', "\\|")
+        ));
+    }
+
+    markdown
+}
+
+/// Prints the entries like a paper tape, the time above each input and the
+/// result aligned to the right
+fn write_pdf(entries: &[Entry], path: &Path) -> Result<(), String> {
+    let (width, height) = PAGE_SIZE;
+    let surface = cairo::pdf::File::new(width, height, path);
+    let cr = cairo::Context::new(&surface);
+    cr.select_font_face("monospace", FontSlant::Normal, FontWeight::Normal);
+    cr.set_font_size(FONT_SIZE);
+    cr.set_line_width(0.5);
+
+    let mut y = PAGE_MARGIN;
+    for entry in entries {
+        let results = result_lines(entry);
+        // Entries aren't split across pages
+        let entry_height = (2 + results.len()) as f64 * LINE_HEIGHT;
+        if y + entry_height > height - PAGE_MARGIN && y > PAGE_MARGIN {
+            cr.show_page();
+            y = PAGE_MARGIN;
+        }
+
+        cr.set_source_rgb(0.5, 0.5, 0.5);
+        y += LINE_HEIGHT;
+        cr.move_to(PAGE_MARGIN, y);
+        cr.show_text(&format_timestamp(entry.timestamp));
+
+        cr.set_source_rgb(0f64, 0f64, 0f64);
+        y += LINE_HEIGHT;
+        cr.move_to(PAGE_MARGIN, y);
+        cr.show_text(&entry.input);
+
+        if entry.result.is_err() {
+            cr.set_source_rgb(0.75, 0.11, 0.16);
+        }
+        for line in results {
+            y += LINE_HEIGHT;
+            let extents = cr.text_extents(&line);
+            cr.move_to(width - PAGE_MARGIN - extents.x_advance, y);
+            cr.show_text(&line);
+        }
+
+        // A dashed line between entries, like a tear-off line
+        y += LINE_HEIGHT / 2f64;
+        cr.set_source_rgb(0.7, 0.7, 0.7);
+        cr.set_dash(&[3f64, 3f64], 0f64);
+        cr.move_to(PAGE_MARGIN, y);
+        cr.line_to(width - PAGE_MARGIN, y);
+        cr.stroke();
+        cr.set_dash(&[], 0f64);
+    }
+
+    surface.finish();
+    match surface.status() {
+        cairo::Status::Success => Ok(()),
+        status => Err(status.to_string()),
+    }
+}
+
+/// `= result` or one line per error
+fn result_lines(entry: &Entry) -> Vec<String> {
+    match entry.result {
+        Ok(ref result) => vec![format!("= {}", result)],
+        Err(ref failures) if failures.is_empty() => vec![String::from("error")],
+        Err(ref failures) => failures
+            .iter()
+            .map(|failure| format!("error: {}", failure.message))
+            .collect(),
+    }
+}
+
+fn error_messages(failures: &[Failure]) -> String {
+    if failures.is_empty() {
+        return String::from("error");
+    }
+
+    let messages: Vec<&str> = failures
+        .iter()
+        .map(|failure| failure.message.as_str())
+        .collect();
+    messages.join("; ")
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Inline code, with a longer delimiter if the text contains backticks
+fn code_span(text: &str) -> String {
+    if text.contains('`') {
+        format!("`` {} ``", text)
+    } else {
+        format!("`{}`", text)
+    }
+}
+
+/// `YYYY-MM-DD hh:mm:ss UTC` for seconds since the Unix epoch
+fn format_timestamp(timestamp: u64) -> String {
+    let (year, month, day) = civil_from_days(timestamp / 86_400);
+    let seconds = timestamp % 86_400;
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// The Gregorian date of a day since the Unix epoch, after Howard Hinnant's
+/// `civil_from_days`
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    // Days since 0000-03-01, years start in March so leap days are last
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = era * 400 + year_of_era + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+fn test_entries() -> Vec<Entry> {
+    vec![
+        Entry {
+            input: String::from("2^10"),
+            result: Ok(String::from("1024")),
+            timestamp: 1_500_000_000,
+        },
+        Entry {
+            input: String::from("foo, \"bar\" | 1"),
+            result: Err(vec![Failure {
+                message: String::from("unknown identifier foo"),
+                start: 0,
+                end: 3,
+            }]),
+            timestamp: 1_500_000_061,
+        },
+    ]
+}
+
+#[test]
+fn test_format_timestamp() {
+    assert_eq!(format_timestamp(0), "1970-01-01 00:00:00 UTC");
+    assert_eq!(format_timestamp(951_825_600), "2000-02-29 12:00:00 UTC");
+    assert_eq!(format_timestamp(1_500_000_000), "2017-07-14 02:40:00 UTC");
+}
+
+#[test]
+fn test_format_from_path() {
+    assert_eq!(Format::from_path(Path::new("tape.PDF")), Format::Pdf);
+    assert_eq!(Format::from_path(Path::new("a/b.csv")), Format::Csv);
+    assert_eq!(Format::from_path(Path::new("notes.md")), Format::Markdown);
+    assert_eq!(Format::from_path(Path::new("history")), Format::Text);
+}
+
+#[test]
+fn test_to_text() {
+    assert_eq!(
+        to_text(&test_entries()),
+        "2017-07-14 02:40:00 UTC\n2^10\n= 1024\n\n\
+         2017-07-14 02:41:01 UTC\nfoo, \"bar\" | 1\nerror: unknown identifier foo\n"
+    );
+}
+
+#[test]
+fn test_to_csv() {
+    assert_eq!(
+        to_csv(&test_entries()),
+        "time,input,result,error\r\n\
+         2017-07-14 02:40:00 UTC,2^10,1024,\r\n\
+         2017-07-14 02:41:01 UTC,\"foo, \"\"bar\"\" | 1\",,unknown identifier foo\r\n"
+    );
+}
+
+#[test]
+fn test_to_markdown() {
+    assert_eq!(
+        to_markdown(&test_entries()),
+        "| Time | Input | Result |\n| --- | --- | --- |\n\
+         | 2017-07-14 02:40:00 UTC | `2^10` | `1024` |\n\
+         | 2017-07-14 02:41:01 UTC | `foo, \"bar\" \\| 1` | **Error:** unknown identifier foo |\n"
+    );
+}
@@ -0,0 +1,202 @@
+//! The history of the window is kept between sessions, one JSON object per
+//! line
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ralculator::data_dir;
+
+use json::{Failure, Json};
+
+/// Number of entries kept in the history file
+pub const MAX_ENTRIES: usize = 1000;
+
+/// An evaluated input
+#[derive(Debug, PartialEq, Clone)]
+pub struct Entry {
+    pub input: String,
+    /// The formatted result or all errors of the input
+    pub result: Result<String, Vec<Failure>>,
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+}
+
+impl Entry {
+    /// An entry evaluated right now
+    pub fn new(input: &str, result: Result<String, Vec<Failure>>) -> Entry {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+
+        Entry {
+            input: input.to_string(),
+            result,
+            timestamp,
+        }
+    }
+
+    /// `{"input", "result", "error", "timestamp"}`, where either `result` or
+    /// `error` is `null`
+    pub fn to_json(&self) -> Json {
+        let (result, error) = match self.result {
+            Ok(ref result) => (Json::String(result.clone()), Json::Null),
+            Err(ref failures) => (
+                Json::Null,
+                Json::Array(failures.iter().map(Failure::to_json).collect()),
+            ),
+        };
+
+        Json::Object(vec![
+            (String::from("input"), Json::String(self.input.clone())),
+            (String::from("result"), result),
+            (String::from("error"), error),
+            (
+                String::from("timestamp"),
+                Json::Number(self.timestamp as f64),
+            ),
+        ])
+    }
+
+    /// Whether the input or result contains `query`, ignoring case
+    pub fn matches(&self, query: &str) -> bool {
+        contains_ignoring_case(&self.input, query)
+            || match self.result {
+                Ok(ref result) => contains_ignoring_case(result, query),
+                Err(_) => false,
+            }
+    }
+
+    pub fn from_json(json: &Json) -> Option<Entry> {
+        let result = match (json.get("result")?, json.get("error")?) {
+            (Json::String(result), Json::Null) => Ok(result.clone()),
+            (Json::Null, Json::Array(failures)) => Err(failures
+                .iter()
+                .map(Failure::from_json)
+                .collect::<Option<Vec<Failure>>>()?),
+            _ => return None,
+        };
+
+        Some(Entry {
+            input: json.get("input")?.as_str()?.to_string(),
+            result,
+            timestamp: json.get("timestamp")?.as_usize()? as u64,
+        })
+    }
+}
+
+pub fn contains_ignoring_case(text: &str, query: &str) -> bool {
+    text.to_lowercase().contains(&query.to_lowercase())
+}
+
+/// `history` in the data directory of ralculator
+pub fn default_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("history"))
+}
+
+/// Reads the entries, a missing file is an empty history. Invalid lines are
+/// skipped.
+pub fn load(path: &Path) -> io::Result<Vec<Entry>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+
+    let mut entries = Vec::new();
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        match Json::parse(&line).ok().as_ref().and_then(Entry::from_json) {
+            Some(entry) => entries.push(entry),
+            None => warn!("Skipping invalid line {} of {}", i + 1, path.display()),
+        }
+    }
+
+    Ok(entries)
+}
+
+pub fn append(path: &Path, entry: &Entry) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", entry.to_json())
+}
+
+/// Replaces the file with the most recent entries
+pub fn save(path: &Path, entries: &[Entry]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut file = File::create(path)?;
+    let skip = entries.len().saturating_sub(MAX_ENTRIES);
+    for entry in &entries[skip..] {
+        writeln!(file, "{}", entry.to_json())?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_entry_json() {
+    let entry = Entry {
+        input: String::from("1 +"),
+        result: Err(vec![Failure {
+            message: String::from("expected (, identifier or number"),
+            start: 3,
+            end: 3,
+        }]),
+        timestamp: 1_500_000_000,
+    };
+
+    assert_eq!(
+        entry.to_json().to_string(),
+        "{\"input\":\"1 +\",\"result\":null,\"error\":[{\"message\":\
+         \"expected (, identifier or number\",\"start\":3,\"end\":3}],\"timestamp\":1500000000}"
+    );
+    assert_eq!(Entry::from_json(&entry.to_json()), Some(entry));
+}
+
+#[test]
+fn test_entry_matches() {
+    let entry = Entry::new("Sqrt(2)", Ok(String::from("1.4142135623730951")));
+    assert!(entry.matches("sqrt"));
+    assert!(entry.matches("1.414"));
+    assert!(entry.matches(""));
+    assert!(!entry.matches("sin"));
+
+    let entry = Entry::new("foo + 1", Err(Vec::new()));
+    assert!(entry.matches("FOO"));
+    assert!(!entry.matches("1.414"));
+}
+
+#[test]
+fn test_save_and_load() {
+    let dir = ::std::env::temp_dir().join(format!("ralculator-test-{}", ::std::process::id()));
+    let path = dir.join("history");
+
+    assert_eq!(load(&path).unwrap(), Vec::new());
+
+    let entries: Vec<Entry> = (0..MAX_ENTRIES + 5)
+        .map(|i| Entry::new(&i.to_string(), Ok(i.to_string())))
+        .collect();
+    save(&path, &entries).unwrap();
+    append(&path, &Entry::new("2^10", Ok(String::from("1024")))).unwrap();
+    append(&path, &Entry::new("1 +", Err(Vec::new()))).unwrap();
+    fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| writeln!(file, "not json"))
+        .unwrap();
+
+    let loaded = load(&path).unwrap();
+    assert_eq!(loaded.len(), MAX_ENTRIES + 2);
+    assert_eq!(loaded[0].input, "5");
+    assert_eq!(loaded[MAX_ENTRIES].result, Ok(String::from("1024")));
+    assert_eq!(loaded[MAX_ENTRIES + 1].result, Err(Vec::new()));
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -1,18 +1,273 @@
 extern crate gtk;
 
 mod app;
+mod dbus;
 mod history;
+mod history_export;
+mod history_file;
+mod plot;
+mod preview;
+mod search_provider;
+mod service;
+
+use std::cell::RefCell;
+use std::ffi::CStr;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use gio::prelude::*;
+use gio::{ApplicationCommandLine, ApplicationFlags, SimpleAction};
+use gio_sys::{g_application_get_dbus_connection, g_application_get_dbus_object_path};
+use glib::translate::ToGlibPtr;
+use glib::Cast;
+use glib::VariantTy;
+use glib_sys::GVariant;
+use gtk::Application;
+
+use ralculator::{parse_and_execute, Context};
+
+use args::{self, Args};
 
 use self::app::App;
+use self::dbus::{Export, Interface, MethodError};
+use self::search_provider::SearchProvider;
+use self::service::{Calculator, CalculatorService};
+
+/// Also the D-Bus name, the name of the desktop file and the D-Bus service
+pub const APPLICATION_ID: &str = "com.github.moritzkn.ralculator";
+
+/// How long the application keeps running without a window after it was
+/// started by D-Bus, in milliseconds
+const INACTIVITY_TIMEOUT: u32 = 10_000;
+
+/// State of the running instance, shared by the window, the actions and the
+/// D-Bus service
+struct Shared {
+    /// Created when the application is first activated
+    app: RefCell<Option<App>>,
+    context: Rc<RefCell<Context>>,
+}
+
+type SharedApp = Rc<Shared>;
+
+impl Calculator for Shared {
+    fn evaluate(&self, expression: &str) -> Result<String, String> {
+        parse_and_execute(expression, &self.context.borrow())
+            .map(|value| value.to_string())
+            .map_err(|err| err.msg)
+    }
+
+    fn variables(&self) -> Vec<(String, f64)> {
+        self.context.borrow().values()
+    }
+
+    fn clear_history(&self) {
+        if let Some(ref app) = *self.app.borrow() {
+            app.clear_history();
+        }
+    }
+}
+
+/// Runs the application and returns its exit code. If ralculator is
+/// already running, the arguments are passed to the running instance and
+/// this returns right away.
+pub fn launch(argv: &[String]) -> i32 {
+    let application = Application::new(APPLICATION_ID, ApplicationFlags::HANDLES_COMMAND_LINE)
+        .expect("Failed to initialize GTK");
+    let app: SharedApp = Rc::new(Shared {
+        app: RefCell::new(None),
+        context: Rc::new(RefCell::new(Context::new())),
+    });
+
+    {
+        let app = app.clone();
+        application.connect_command_line(move |application, command_line| {
+            handle_command_line(application, command_line, &app)
+        });
+    }
+
+    // Emitted instead of `command-line` when started through D-Bus, e.g. from
+    // the desktop file
+    {
+        let app = app.clone();
+        application.connect_activate(move |application| {
+            with_app(application, &app, None, App::present);
+        });
+    }
+
+    add_actions(&application, &app);
+
+    // Only the primary instance is started up
+    let exports: Rc<RefCell<Vec<Export>>> = Rc::new(RefCell::new(Vec::new()));
+    {
+        let exports = exports.clone();
+        application.connect_startup(move |application| {
+            *exports.borrow_mut() = export_interfaces(application, &app);
+        });
+    }
+    application.connect_shutdown(move |_| {
+        exports.borrow_mut().clear();
+    });
+
+    application.set_inactivity_timeout(INACTIVITY_TIMEOUT);
+    application.run(argv)
+}
+
+/// Exports the calculator next to the interfaces of GApplication and the
+/// search provider at `<object path>/SearchProvider`
+fn export_interfaces(application: &Application, app: &SharedApp) -> Vec<Export> {
+    let search_provider = {
+        let application = application.clone();
+        let app = app.clone();
+
+        SearchProvider::new(app.context.clone(), move |expression| {
+            show_evaluated(&application, &app, expression)
+        })
+    };
+
+    let interfaces: Vec<(&str, Rc<dyn Interface>)> = vec![
+        ("", Rc::new(CalculatorService(app.clone()))),
+        ("/SearchProvider", Rc::new(search_provider)),
+    ];
+
+    let application: &gio::Application = application.upcast_ref();
+    let mut exports = Vec::new();
+
+    unsafe {
+        let connection = g_application_get_dbus_connection(application.to_glib_none().0);
+        // Without a session bus
+        if connection.is_null() {
+            return exports;
+        }
+
+        let object_path = g_application_get_dbus_object_path(application.to_glib_none().0);
+        let object_path = CStr::from_ptr(object_path).to_string_lossy();
+
+        for (suffix, interface) in interfaces {
+            let held = Rc::new(Held {
+                application: application.clone(),
+                interface,
+            });
+            let path = format!("{}{}", object_path, suffix);
+
+            match Export::register(connection, &path, held) {
+                Ok(export) => exports.push(export),
+                Err(err) => error!("Exporting {} failed: {}", path, err),
+            }
+        }
+    }
+
+    exports
+}
+
+/// Keeps the application running while a call is handled, so it doesn't
+/// quit right after it was started by D-Bus
+struct Held {
+    application: gio::Application,
+    interface: Rc<dyn Interface>,
+}
+
+impl Interface for Held {
+    fn name(&self) -> &'static str {
+        self.interface.name()
+    }
+
+    fn xml(&self) -> &'static str {
+        self.interface.xml()
+    }
+
+    unsafe fn call(
+        &self,
+        method: &str,
+        parameters: *mut GVariant,
+    ) -> Result<*mut GVariant, MethodError> {
+        self.application.hold();
+        let reply = self.interface.call(method, parameters);
+        self.application.release();
+        reply
+    }
+}
+
+/// Opens the window with the expression evaluated
+fn show_evaluated(application: &Application, app: &SharedApp, expression: &str) {
+    with_app(application, app, None, |app| {
+        app.evaluate(expression);
+        app.present();
+    });
+}
+
+/// `app.evaluate(s)` evaluates the expression in the window
+fn add_actions(application: &Application, app: &SharedApp) {
+    let evaluate = SimpleAction::new("evaluate", VariantTy::new("s").unwrap());
+    {
+        let application = application.clone();
+        let app = app.clone();
+
+        evaluate.connect_activate(move |_, parameter| {
+            let expr = match parameter.as_ref().and_then(|p| p.get_str()) {
+                Some(expr) => expr.to_string(),
+                None => return,
+            };
+
+            show_evaluated(&application, &app, &expr);
+        });
+    }
+    application.add_action(&evaluate);
+}
+
+/// Calls `f` with the window, creating it if it doesn't exist yet
+fn with_app<F: FnOnce(&App)>(
+    application: &Application,
+    app: &SharedApp,
+    history_file: Option<&PathBuf>,
+    f: F,
+) {
+    let context = app.context.clone();
+    let mut app = app.app.borrow_mut();
+
+    if app.is_none() {
+        *app = Some(App::setup_new(application, context, history_file));
+    } else if let Some(path) = history_file {
+        warn!(
+            "Ignoring history file {}, the window is already open",
+            path.display()
+        );
+    }
+
+    f(app.as_ref().unwrap());
+}
+
+fn handle_command_line(
+    application: &Application,
+    command_line: &ApplicationCommandLine,
+    app: &SharedApp,
+) -> i32 {
+    let arguments = command_line
+        .get_arguments()
+        .into_iter()
+        .skip(1)
+        .map(|arg| arg.to_string_lossy().into_owned());
+
+    // The arguments were checked before they were sent to this instance
+    let args: Args = match args::parse(arguments) {
+        Ok(args) => args,
+        Err(msg) => {
+            error!("Invalid arguments: {}", msg);
+            return 2;
+        }
+    };
+
+    with_app(application, app, args.history_file.as_ref(), |app| {
+        if let Some(mode) = args.mode {
+            app.set_mode(mode);
+        }
 
-pub fn launch() {
-    gtk::init().expect("Failed to initialize GTK");
+        if let Some(ref expr) = args.expr {
+            app.evaluate(expr);
+        }
 
-    let app = App::setup_new();
-    app.on_close(|| {
-        gtk::main_quit();
-        gtk::Inhibit(false)
+        app.present();
     });
 
-    gtk::main();
+    0
 }
@@ -0,0 +1,429 @@
+use std::cell::RefCell;
+use std::fs::File;
+use std::path::Path;
+use std::rc::Rc;
+
+use cairo;
+use cairo::prelude::*;
+use cairo::{FontSlant, FontWeight, Format, ImageSurface};
+use gdk::{EventType, ModifierType, ScrollDirection};
+use gtk::prelude::*;
+use gtk::DrawingArea;
+
+use ralculator::{value_range, Context, Plot};
+
+/// Colors of the plotted functions, used in order
+static SERIES_COLORS: [(f64, f64, f64); 5] = [
+    (0.24, 0.43, 0.71),
+    (0.80, 0.30, 0.20),
+    (0.25, 0.60, 0.30),
+    (0.55, 0.35, 0.65),
+    (0.85, 0.60, 0.10),
+];
+
+/// Zoom per scroll step
+const ZOOM_FACTOR: f64 = 1.25;
+/// Rough distance between two grid lines in pixels
+const GRID_SPACING: f64 = 80f64;
+const FONT_SIZE: f64 = 11f64;
+/// Number of samples used to scale the value axis to a new plot
+const SCALING_SAMPLES: usize = 500;
+/// Limit of grid lines per axis, in case the range and step are off
+const MAX_TICKS: usize = 100;
+/// Smallest visible span of an axis relative to the values at its center,
+/// zooming in further would exhaust the precision of `f64`
+const MIN_RELATIVE_SPAN: f64 = 1e-9;
+/// Largest visible span of an axis
+const MAX_SPAN: f64 = 1e15;
+
+/// The part of the plane that is visible
+#[derive(Debug, Copy, Clone)]
+struct Viewport {
+    x: (f64, f64),
+    y: (f64, f64),
+}
+
+impl Viewport {
+    fn to_screen(self, (x, y): (f64, f64), width: f64, height: f64) -> (f64, f64) {
+        (
+            (x - self.x.0) / (self.x.1 - self.x.0) * width,
+            height - (y - self.y.0) / (self.y.1 - self.y.0) * height,
+        )
+    }
+
+    fn to_plane(self, (x, y): (f64, f64), width: f64, height: f64) -> (f64, f64) {
+        (
+            self.x.0 + x / width * (self.x.1 - self.x.0),
+            self.y.0 + (height - y) / height * (self.y.1 - self.y.0),
+        )
+    }
+
+    /// Whether both axes can be drawn with grid lines and labels, i.e. they
+    /// are neither too small for the precision of `f64` nor too large
+    fn is_drawable(self) -> bool {
+        [self.x, self.y].iter().all(|&(start, end)| {
+            let span = end - start;
+            let center = ((start + end) / 2f64).abs().max(1f64);
+            span.is_finite() && span >= center * MIN_RELATIVE_SPAN && span <= MAX_SPAN
+        })
+    }
+
+    /// Scales the viewport by `factor` while keeping `center` in place
+    fn zoom(self, center: (f64, f64), factor: f64) -> Viewport {
+        Viewport {
+            x: (
+                center.0 + (self.x.0 - center.0) * factor,
+                center.0 + (self.x.1 - center.0) * factor,
+            ),
+            y: (
+                center.1 + (self.y.0 - center.1) * factor,
+                center.1 + (self.y.1 - center.1) * factor,
+            ),
+        }
+    }
+}
+
+struct PlotState {
+    plot: Plot,
+    /// The context at the time the plot was created, so e.g. changing the
+    /// angle mode doesn't change the plot
+    context: Context,
+    viewport: Viewport,
+    /// Pointer position and viewport at the start of a drag
+    drag_start: Option<((f64, f64), Viewport)>,
+}
+
+impl PlotState {
+    /// The viewport that shows the plot range and all values in it
+    fn initial_viewport(&self) -> Viewport {
+        let samples =
+            self.plot
+                .sample(&self.context, self.plot.from, self.plot.to, SCALING_SAMPLES);
+
+        Viewport {
+            x: (self.plot.from, self.plot.to),
+            y: value_range(&samples),
+        }
+    }
+}
+
+/// A drawing area showing the result of `plot(...)`, drag to pan, scroll to
+/// zoom and double click to reset the view
+#[derive(Clone)]
+pub struct PlotView {
+    panel: gtk::Box,
+    area: DrawingArea,
+    state: Rc<RefCell<Option<PlotState>>>,
+}
+
+impl PlotView {
+    pub fn new(panel: gtk::Box, area: DrawingArea) -> PlotView {
+        let view = PlotView {
+            panel,
+            area,
+            state: Rc::new(RefCell::new(None)),
+        };
+        view.connect_events();
+        view
+    }
+
+    fn connect_events(&self) {
+        let state = self.state.clone();
+        self.area.connect_draw(move |area, cr| {
+            if let Some(ref state) = *state.borrow() {
+                let width = f64::from(area.get_allocated_width());
+                let height = f64::from(area.get_allocated_height());
+                draw(cr, state, width, height);
+            }
+            Inhibit(false)
+        });
+
+        let state = self.state.clone();
+        self.area.connect_button_press_event(move |area, event| {
+            if event.get_button() != 1 {
+                return Inhibit(false);
+            }
+
+            if let Some(ref mut state) = *state.borrow_mut() {
+                if event.get_event_type() == EventType::DoubleButtonPress {
+                    state.viewport = state.initial_viewport();
+                    area.queue_draw();
+                } else {
+                    state.drag_start = Some((event.get_position(), state.viewport));
+                }
+            }
+            Inhibit(true)
+        });
+
+        let state = self.state.clone();
+        self.area.connect_motion_notify_event(move |area, event| {
+            if !event.get_state().contains(ModifierType::BUTTON1_MASK) {
+                return Inhibit(false);
+            }
+
+            if let Some(ref mut state) = *state.borrow_mut() {
+                if let Some(((start_x, start_y), start)) = state.drag_start {
+                    let (x, y) = event.get_position();
+                    let width = f64::from(area.get_allocated_width());
+                    let height = f64::from(area.get_allocated_height());
+                    let dx = (x - start_x) / width * (start.x.1 - start.x.0);
+                    let dy = (y - start_y) / height * (start.y.1 - start.y.0);
+
+                    state.viewport = Viewport {
+                        x: (start.x.0 - dx, start.x.1 - dx),
+                        y: (start.y.0 + dy, start.y.1 + dy),
+                    };
+                    area.queue_draw();
+                }
+            }
+            Inhibit(true)
+        });
+
+        let state = self.state.clone();
+        self.area.connect_scroll_event(move |area, event| {
+            let factor = match event.get_direction() {
+                ScrollDirection::Up => 1f64 / ZOOM_FACTOR,
+                ScrollDirection::Down => ZOOM_FACTOR,
+                ScrollDirection::Smooth => ZOOM_FACTOR.powf(event.get_delta().1),
+                _ => return Inhibit(false),
+            };
+
+            if let Some(ref mut state) = *state.borrow_mut() {
+                let width = f64::from(area.get_allocated_width());
+                let height = f64::from(area.get_allocated_height());
+                let center = state.viewport.to_plane(event.get_position(), width, height);
+
+                let zoomed = state.viewport.zoom(center, factor);
+                if zoomed.is_drawable() {
+                    state.viewport = zoomed;
+                    area.queue_draw();
+                }
+            }
+            Inhibit(true)
+        });
+    }
+
+    /// Shows the panel with the plot, the view is scaled to fit the plot
+    pub fn show(&self, plot: Plot, context: &Context) {
+        self.panel.show();
+
+        let mut state = PlotState {
+            plot,
+            context: context.clone(),
+            viewport: Viewport {
+                x: (0f64, 1f64),
+                y: (0f64, 1f64),
+            },
+            drag_start: None,
+        };
+        state.viewport = state.initial_viewport();
+
+        *self.state.borrow_mut() = Some(state);
+        self.area.queue_draw();
+    }
+
+    /// Writes the current view to a file, SVG if the file name ends with
+    /// `.svg` and PNG otherwise
+    pub fn export(&self, path: &Path) -> Result<(), String> {
+        let state = self.state.borrow();
+        let state = match *state {
+            Some(ref state) => state,
+            None => return Err(String::from("nothing has been plotted yet")),
+        };
+
+        let width = self.area.get_allocated_width().max(1);
+        let height = self.area.get_allocated_height().max(1);
+
+        if path.extension().and_then(|extension| extension.to_str()) == Some("svg") {
+            let surface = cairo::svg::File::new(f64::from(width), f64::from(height), path);
+            draw(
+                &cairo::Context::new(&surface),
+                state,
+                f64::from(width),
+                f64::from(height),
+            );
+            surface.finish();
+
+            match surface.status() {
+                cairo::Status::Success => Ok(()),
+                status => Err(status.to_string()),
+            }
+        } else {
+            let surface = ImageSurface::create(Format::ARgb32, width, height)
+                .map_err(|status| status.to_string())?;
+            draw(
+                &cairo::Context::new(&surface),
+                state,
+                f64::from(width),
+                f64::from(height),
+            );
+
+            let mut file = File::create(path).map_err(|err| err.to_string())?;
+            surface
+                .write_to_png(&mut file)
+                .map_err(|err| err.to_string())
+        }
+    }
+}
+
+fn draw(cr: &cairo::Context, state: &PlotState, width: f64, height: f64) {
+    let viewport = state.viewport;
+
+    cr.set_source_rgb(1f64, 1f64, 1f64);
+    cr.paint();
+
+    cr.select_font_face("sans-serif", FontSlant::Normal, FontWeight::Normal);
+    cr.set_font_size(FONT_SIZE);
+    cr.set_line_width(1f64);
+
+    // Grid and labels
+    cr.set_source_rgb(0.9, 0.9, 0.9);
+    let x_ticks = ticks(viewport.x, width / GRID_SPACING);
+    let y_ticks = ticks(viewport.y, height / GRID_SPACING);
+    for &(x, _) in &x_ticks {
+        let (sx, _) = viewport.to_screen((x, 0f64), width, height);
+        cr.move_to(sx.round() + 0.5, 0f64);
+        cr.line_to(sx.round() + 0.5, height);
+    }
+    for &(y, _) in &y_ticks {
+        let (_, sy) = viewport.to_screen((0f64, y), width, height);
+        cr.move_to(0f64, sy.round() + 0.5);
+        cr.line_to(width, sy.round() + 0.5);
+    }
+    cr.stroke();
+
+    // Axes, kept at the border when the origin isn't visible
+    let (origin_x, origin_y) = viewport.to_screen((0f64, 0f64), width, height);
+    let origin_x = origin_x.max(0f64).min(width - 1f64).round() + 0.5;
+    let origin_y = origin_y.max(0f64).min(height - 1f64).round() + 0.5;
+
+    cr.set_source_rgb(0.4, 0.4, 0.4);
+    cr.move_to(origin_x, 0f64);
+    cr.line_to(origin_x, height);
+    cr.move_to(0f64, origin_y);
+    cr.line_to(width, origin_y);
+    cr.stroke();
+
+    for &(x, ref label) in &x_ticks {
+        let (sx, _) = viewport.to_screen((x, 0f64), width, height);
+        cr.move_to(sx + 3f64, (origin_y + FONT_SIZE + 2f64).min(height - 3f64));
+        cr.show_text(label);
+    }
+    for &(y, ref label) in &y_ticks {
+        let (_, sy) = viewport.to_screen((0f64, y), width, height);
+        let extents = cr.text_extents(label);
+        let x = if origin_x + 3f64 + extents.width > width {
+            origin_x - 3f64 - extents.width
+        } else {
+            origin_x + 3f64
+        };
+        cr.move_to(x, sy - 3f64);
+        cr.show_text(label);
+    }
+
+    // Functions
+    let samples = state
+        .plot
+        .sample(&state.context, viewport.x.0, viewport.x.1, width as usize);
+    cr.set_line_width(2f64);
+
+    for (i, series) in samples.iter().enumerate() {
+        let (r, g, b) = SERIES_COLORS[i % SERIES_COLORS.len()];
+        cr.set_source_rgb(r, g, b);
+
+        let mut previous: Option<(f64, f64)> = None;
+        for &point in series {
+            if !point.1.is_finite() {
+                previous = None;
+                continue;
+            }
+
+            // Far away points would overflow cairo's fixed point coordinates
+            let (sx, sy) = viewport.to_screen(point, width, height);
+            let sy = sy.max(-height).min(2f64 * height);
+
+            match previous {
+                // Jumping from one edge to the other is a pole, not a steep line
+                Some((_, previous_y))
+                    if (previous_y <= -height && sy >= 2f64 * height)
+                        || (previous_y >= 2f64 * height && sy <= -height) =>
+                {
+                    cr.move_to(sx, sy)
+                }
+                Some(_) => cr.line_to(sx, sy),
+                None => cr.move_to(sx, sy),
+            }
+            previous = Some((sx, sy));
+        }
+        cr.stroke();
+    }
+
+    // Legend
+    for (i, function) in state.plot.functions.iter().enumerate() {
+        let (r, g, b) = SERIES_COLORS[i % SERIES_COLORS.len()];
+        let y = 8f64 + (i as f64 + 1f64) * (FONT_SIZE + 4f64);
+
+        cr.set_source_rgb(r, g, b);
+        cr.rectangle(8f64, y - FONT_SIZE + 2f64, 10f64, FONT_SIZE - 4f64);
+        cr.fill();
+
+        cr.set_source_rgb(0.2, 0.2, 0.2);
+        cr.move_to(24f64, y);
+        cr.show_text(&function.to_string());
+    }
+}
+
+/// Evenly spaced values at multiples of 1, 2 or 5 times a power of ten,
+/// together with their labels
+fn ticks(range: (f64, f64), max_count: f64) -> Vec<(f64, String)> {
+    let raw_step = (range.1 - range.0) / max_count.max(1f64);
+    if !raw_step.is_finite() || raw_step <= 0f64 {
+        return Vec::new();
+    }
+
+    let magnitude = 10f64.powf(raw_step.log10().floor());
+    let step = [1f64, 2f64, 5f64, 10f64]
+        .iter()
+        .map(|factor| factor * magnitude)
+        .find(|step| *step >= raw_step)
+        .unwrap_or(10f64 * magnitude);
+    let decimals = (-step.log10().floor()).max(0f64) as usize;
+
+    // Counting in floats would get stuck once `first` exceeds 2^53
+    let first = (range.0 / step).ceil();
+    (0..MAX_TICKS)
+        .map(|n| (first + n as f64) * step)
+        .take_while(|&value| value <= range.1)
+        .map(|value| {
+            // Avoids labels like `-0`
+            let value = if value == 0f64 { 0f64 } else { value };
+            (value, format!("{:.*}", decimals, value))
+        })
+        .collect()
+}
+
+#[test]
+fn test_ticks() {
+    let labels: Vec<String> = ticks((-1f64, 1f64), 4f64)
+        .into_iter()
+        .map(|(_, label)| label)
+        .collect();
+    assert_eq!(labels, vec!["-1.0", "-0.5", "0.0", "0.5", "1.0"]);
+
+    // Beyond 2^53 adding 1 to the tick index doesn't change it
+    let start = 1e17;
+    assert!(ticks((start, start + 1e-1), 10f64).len() <= MAX_TICKS);
+}
+
+#[test]
+fn test_zoom_limits() {
+    let viewport = Viewport {
+        x: (-1f64, 1f64),
+        y: (-1f64, 1f64),
+    };
+    assert!(viewport.is_drawable());
+    assert!(viewport.zoom((0f64, 0f64), 1e-8).is_drawable());
+    assert!(!viewport.zoom((0f64, 0f64), 1e-10).is_drawable());
+    assert!(!viewport.zoom((1e6, 0f64), 1e-6).is_drawable());
+    assert!(!viewport.zoom((0f64, 0f64), 1e16).is_drawable());
+}
@@ -0,0 +1,136 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::thread;
+
+use glib::{Continue, MainContext, PRIORITY_DEFAULT};
+use gtk::prelude::*;
+use gtk::Label;
+
+use ralculator::{is_expensive, parse, parse_and_execute, Context, InputError, Value};
+
+/// Milliseconds without changes before an expensive expression is evaluated
+const DEBOUNCE_DELAY: u32 = 300;
+
+/// An expensive input waiting to be evaluated in the background
+struct Job {
+    generation: u64,
+    input: String,
+    context: Context,
+}
+
+/// Shows the result of the input while it is typed
+#[derive(Clone)]
+pub struct Preview {
+    label: Label,
+    /// Incremented on every update, results of older updates are dropped
+    generation: Rc<Cell<u64>>,
+    /// Whether a background evaluation is running, only one runs at a time
+    running: Rc<Cell<bool>>,
+    /// The latest input that arrived while an evaluation was running
+    pending: Rc<RefCell<Option<Job>>>,
+}
+
+impl Preview {
+    pub fn new(label: Label) -> Preview {
+        Preview {
+            label,
+            generation: Rc::new(Cell::new(0)),
+            running: Rc::new(Cell::new(false)),
+            pending: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    /// Cheap expressions are evaluated right away, expensive ones in the
+    /// background once the input didn't change for a moment
+    pub fn update(&self, input: &str, context: &Context) {
+        let generation = self.generation.get() + 1;
+        self.generation.set(generation);
+
+        let input = input.trim().to_string();
+        if input.is_empty() {
+            self.show_text("", false);
+            return;
+        }
+
+        let expensive = match parse(&input) {
+            Ok(ref root) => is_expensive(root),
+            Err(_) => false,
+        };
+        if !expensive {
+            self.show(parse_and_execute(&input, context));
+            return;
+        }
+
+        self.show_text("…", false);
+
+        let preview = self.clone();
+        let context = context.clone();
+        gtk::timeout_add(DEBOUNCE_DELAY, move || {
+            if preview.generation.get() == generation {
+                preview.evaluate_in_background(Job {
+                    generation,
+                    input: input.clone(),
+                    context: context.clone(),
+                });
+            }
+            Continue(false)
+        });
+    }
+
+    /// Starts the job unless another one is running, then it waits until
+    /// that one is done. Jobs can't be cancelled, so this keeps stale ones
+    /// from piling up.
+    fn evaluate_in_background(&self, job: Job) {
+        if self.running.get() {
+            *self.pending.borrow_mut() = Some(job);
+            return;
+        }
+        self.running.set(true);
+
+        let (sender, receiver) = MainContext::channel(PRIORITY_DEFAULT);
+        let Job {
+            generation,
+            input,
+            context,
+        } = job;
+
+        thread::spawn(move || {
+            // The receiver is gone if the window was closed in the meantime
+            let _ = sender.send(parse_and_execute(&input, &context));
+        });
+
+        let preview = self.clone();
+        receiver.attach(None, move |result| {
+            preview.running.set(false);
+
+            let current = preview.generation.get();
+            if current == generation {
+                preview.show(result);
+            }
+
+            let pending = preview.pending.borrow_mut().take();
+            if let Some(job) = pending.filter(|job| job.generation == current) {
+                preview.evaluate_in_background(job);
+            }
+            Continue(false)
+        });
+    }
+
+    fn show(&self, result: Result<Value, InputError>) {
+        match result {
+            Ok(value) => self.show_text(&format!("= {}", value), false),
+            Err(err) => self.show_text(&err.msg, true),
+        }
+    }
+
+    fn show_text(&self, text: &str, is_error: bool) {
+        self.label.set_text(text);
+
+        let style = self.label.get_style_context();
+        if is_error {
+            style.add_class("preview-error");
+        } else {
+            style.remove_class("preview-error");
+        }
+    }
+}
@@ -0,0 +1,275 @@
+//! The `org.gnome.Shell.SearchProvider2` D-Bus interface, which shows results
+//! of expressions typed into the search of the GNOME Shell overview
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use glib_sys::{g_variant_new_variant, GVariant};
+
+use ralculator::{evaluate, is_expensive, parse, Context, Value};
+
+use super::dbus::{
+    get_string, get_strings, new_array, new_dict, new_string, new_strings, new_tuple, Interface,
+    MethodError,
+};
+
+static INTERFACE_XML: &str = "\
+<node>
+  <interface name='org.gnome.Shell.SearchProvider2'>
+    <method name='GetInitialResultSet'>
+      <arg name='terms' type='as' direction='in'/>
+      <arg name='results' type='as' direction='out'/>
+    </method>
+    <method name='GetSubsearchResultSet'>
+      <arg name='previous_results' type='as' direction='in'/>
+      <arg name='terms' type='as' direction='in'/>
+      <arg name='results' type='as' direction='out'/>
+    </method>
+    <method name='GetResultMetas'>
+      <arg name='identifiers' type='as' direction='in'/>
+      <arg name='metas' type='aa{sv}' direction='out'/>
+    </method>
+    <method name='ActivateResult'>
+      <arg name='identifier' type='s' direction='in'/>
+      <arg name='terms' type='as' direction='in'/>
+      <arg name='timestamp' type='u' direction='in'/>
+    </method>
+    <method name='LaunchSearch'>
+      <arg name='terms' type='as' direction='in'/>
+      <arg name='timestamp' type='u' direction='in'/>
+    </method>
+  </interface>
+</node>";
+
+/// Results are identified by their expression
+pub struct SearchProvider {
+    context: Rc<RefCell<Context>>,
+    /// Opens the window with the expression evaluated
+    activate: Box<dyn Fn(&str)>,
+}
+
+impl SearchProvider {
+    pub fn new<F: Fn(&str) + 'static>(
+        context: Rc<RefCell<Context>>,
+        activate: F,
+    ) -> SearchProvider {
+        SearchProvider {
+            context,
+            activate: Box::new(activate),
+        }
+    }
+
+    fn search(&self, terms: &[String]) -> Vec<String> {
+        let expression = terms.join(" ");
+
+        match search_result(&expression, &self.context.borrow()) {
+            Some(_) => vec![expression],
+            None => Vec::new(),
+        }
+    }
+
+    unsafe fn result_meta(&self, expression: &str) -> *mut GVariant {
+        let result = search_result(expression, &self.context.borrow()).unwrap_or_default();
+
+        new_dict(
+            "a{sv}",
+            vec![
+                (
+                    String::from("id"),
+                    g_variant_new_variant(new_string(expression)),
+                ),
+                (
+                    String::from("name"),
+                    g_variant_new_variant(new_string(&result)),
+                ),
+                (
+                    String::from("description"),
+                    g_variant_new_variant(new_string(expression)),
+                ),
+                (
+                    String::from("clipboardText"),
+                    g_variant_new_variant(new_string(&result)),
+                ),
+            ],
+        )
+    }
+}
+
+impl Interface for SearchProvider {
+    fn name(&self) -> &'static str {
+        "org.gnome.Shell.SearchProvider2"
+    }
+
+    fn xml(&self) -> &'static str {
+        INTERFACE_XML
+    }
+
+    unsafe fn call(
+        &self,
+        method: &str,
+        parameters: *mut GVariant,
+    ) -> Result<*mut GVariant, MethodError> {
+        let reply = match method {
+            "GetInitialResultSet" => {
+                new_tuple(&[new_strings(&self.search(&get_strings(parameters, 0)))])
+            }
+            "GetSubsearchResultSet" => {
+                new_tuple(&[new_strings(&self.search(&get_strings(parameters, 1)))])
+            }
+            "GetResultMetas" => {
+                let metas = get_strings(parameters, 0)
+                    .iter()
+                    .map(|expression| self.result_meta(expression))
+                    .collect();
+                new_tuple(&[new_array("aa{sv}", metas)])
+            }
+            "ActivateResult" => {
+                (self.activate)(&get_string(parameters, 0));
+                new_tuple(&[])
+            }
+            "LaunchSearch" => {
+                (self.activate)(&get_strings(parameters, 0).join(" "));
+                new_tuple(&[])
+            }
+            _ => return Err(MethodError::UnknownMethod),
+        };
+
+        Ok(reply)
+    }
+}
+
+/// Characters of which a calculation has at least one
+static CALCULATION_CHARS: &[char] = &[
+    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', '+', '-', '*', '/', '^', '!', '=',
+];
+
+/// The formatted result if the text is a calculation, most searches aren't.
+/// Expressions that may take long aren't evaluated, the shell waits for the
+/// results.
+fn search_result(text: &str, context: &Context) -> Option<String> {
+    let text = text.trim();
+
+    // Searches for e.g. `e` or `pi` are looking for something else
+    if !text.contains(CALCULATION_CHARS) {
+        return None;
+    }
+
+    let root = parse(text).ok()?;
+    if is_expensive(&root) {
+        return None;
+    }
+
+    match evaluate(&root, context) {
+        Ok(Value::Plot(_)) | Err(_) => None,
+        Ok(value) => {
+            let result = value.to_string();
+            // A number alone isn't a calculation
+            if result == text {
+                None
+            } else {
+                Some(result)
+            }
+        }
+    }
+}
+
+#[test]
+fn test_search_result() {
+    let context = Context::new();
+    assert_eq!(
+        search_result("2*pi*3", &context),
+        Some(String::from("18.849555921538759"))
+    );
+    assert_eq!(
+        search_result(" sin(30deg) ", &context),
+        Some(String::from("1/2"))
+    );
+    assert_eq!(search_result("42", &context), None);
+    assert_eq!(search_result("firefox", &context), None);
+    assert_eq!(search_result("plot(sin(x), 0, 1)", &context), None);
+    assert_eq!(search_result("pi", &context), None);
+    assert_eq!(search_result("e", &context), None);
+    assert_eq!(search_result("solve(x^2 = 4, x)", &context), None);
+    assert_eq!(search_result("integrate(x, x, 0, 1)", &context), None);
+}
+
+#[test]
+fn test_search_provider_methods() {
+    use super::dbus::call_directly;
+
+    let activated = Rc::new(RefCell::new(Vec::new()));
+    let provider = {
+        let activated = activated.clone();
+        SearchProvider::new(Rc::new(RefCell::new(Context::new())), move |expression| {
+            activated.borrow_mut().push(expression.to_string())
+        })
+    };
+    let call = |method, args| call_directly(&provider, method, args).unwrap();
+
+    assert_eq!(
+        call("GetInitialResultSet", "(['2*pi*3'],)"),
+        "(['2*pi*3'],)"
+    );
+    assert_eq!(call("GetInitialResultSet", "(['pi'],)"), "(@as [],)");
+    assert_eq!(
+        call("GetSubsearchResultSet", "(['2*pi'], ['2*pi', '*3'])"),
+        "(['2*pi *3'],)"
+    );
+    assert_eq!(
+        call("GetResultMetas", "(['2^10'],)"),
+        "([{'id': <'2^10'>, 'name': <'1024'>, 'description': <'2^10'>, 'clipboardText': <'1024'>}],)"
+    );
+    assert_eq!(call("ActivateResult", "('2^10', ['2^10'], uint32 0)"), "()");
+    assert_eq!(call("LaunchSearch", "(['1', '+ 1'], uint32 0)"), "()");
+    assert_eq!(*activated.borrow(), vec!["2^10", "1 + 1"]);
+}
+
+#[test]
+#[ignore = "needs dbus-daemon and gdbus, run with --ignored"]
+fn test_search_provider() {
+    use super::dbus::{Export, TestBus};
+
+    let bus = TestBus::start();
+
+    let activated = Rc::new(RefCell::new(Vec::new()));
+    let provider = {
+        let activated = activated.clone();
+        SearchProvider::new(Rc::new(RefCell::new(Context::new())), move |expression| {
+            activated.borrow_mut().push(expression.to_string())
+        })
+    };
+    let export = unsafe { Export::register(bus.connection, "/test", Rc::new(provider)).unwrap() };
+
+    let method = |name: &str| format!("org.gnome.Shell.SearchProvider2.{}", name);
+
+    assert_eq!(
+        bus.call(&method("GetInitialResultSet"), &["['2*pi*3']"]),
+        "(['2*pi*3'],)"
+    );
+    assert_eq!(
+        bus.call(&method("GetInitialResultSet"), &["['firefox']"]),
+        "(@as [],)"
+    );
+    assert_eq!(
+        bus.call(
+            &method("GetSubsearchResultSet"),
+            &["['2*pi']", "['2*pi', '*3']"]
+        ),
+        "(['2*pi *3'],)"
+    );
+    assert_eq!(
+        bus.call(&method("GetResultMetas"), &["['2^10']"]),
+        "([{'id': <'2^10'>, 'name': <'1024'>, 'description': <'2^10'>, 'clipboardText': <'1024'>}],)"
+    );
+    assert_eq!(
+        bus.call(&method("ActivateResult"), &["'2^10'", "['2^10']", "0"]),
+        "()"
+    );
+    assert_eq!(
+        bus.call(&method("LaunchSearch"), &["['1', '+ 1']", "0"]),
+        "()"
+    );
+    assert_eq!(*activated.borrow(), vec!["2^10", "1 + 1"]);
+
+    drop(export);
+}
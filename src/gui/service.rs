@@ -0,0 +1,161 @@
+//! The `com.github.moritzkn.ralculator.Calculator` D-Bus interface, which
+//! lets other programs compute through a running instance
+
+use std::rc::Rc;
+
+use glib_sys::{g_variant_new_double, GVariant};
+
+use super::dbus::{get_string, new_dict, new_string, new_tuple, Interface, MethodError};
+
+static INTERFACE_XML: &str = "\
+<node>
+  <interface name='com.github.moritzkn.ralculator.Calculator'>
+    <method name='Evaluate'>
+      <arg name='expression' type='s' direction='in'/>
+      <arg name='result' type='s' direction='out'/>
+    </method>
+    <method name='ListVariables'>
+      <arg name='variables' type='a{sd}' direction='out'/>
+    </method>
+    <method name='ClearHistory'/>
+  </interface>
+</node>";
+
+/// What the service does on behalf of its callers
+pub trait Calculator {
+    /// The formatted result or the error message
+    fn evaluate(&self, expression: &str) -> Result<String, String>;
+    /// The built-in constants and the variables
+    fn variables(&self) -> Vec<(String, f64)>;
+    fn clear_history(&self);
+}
+
+pub struct CalculatorService(pub Rc<dyn Calculator>);
+
+impl Interface for CalculatorService {
+    fn name(&self) -> &'static str {
+        "com.github.moritzkn.ralculator.Calculator"
+    }
+
+    fn xml(&self) -> &'static str {
+        INTERFACE_XML
+    }
+
+    unsafe fn call(
+        &self,
+        method: &str,
+        parameters: *mut GVariant,
+    ) -> Result<*mut GVariant, MethodError> {
+        let reply = match method {
+            "Evaluate" => {
+                let result = self
+                    .0
+                    .evaluate(&get_string(parameters, 0))
+                    .map_err(MethodError::InvalidArgs)?;
+                new_tuple(&[new_string(&result)])
+            }
+            "ListVariables" => {
+                let variables = self
+                    .0
+                    .variables()
+                    .into_iter()
+                    .map(|(name, value)| (name, g_variant_new_double(value)))
+                    .collect();
+                new_tuple(&[new_dict("a{sd}", variables)])
+            }
+            "ClearHistory" => {
+                self.0.clear_history();
+                new_tuple(&[])
+            }
+            _ => return Err(MethodError::UnknownMethod),
+        };
+
+        Ok(reply)
+    }
+}
+
+#[cfg(test)]
+struct TestCalculator {
+    cleared: ::std::cell::Cell<bool>,
+}
+
+#[cfg(test)]
+impl Calculator for TestCalculator {
+    fn evaluate(&self, expression: &str) -> Result<String, String> {
+        ::ralculator::parse_and_execute(expression, &::ralculator::Context::new())
+            .map(|value| value.to_string())
+            .map_err(|err| err.msg)
+    }
+
+    fn variables(&self) -> Vec<(String, f64)> {
+        ::ralculator::Context::new().values()
+    }
+
+    fn clear_history(&self) {
+        self.cleared.set(true);
+    }
+}
+
+#[test]
+fn test_calculator_service_methods() {
+    use super::dbus::call_directly;
+
+    let calculator = Rc::new(TestCalculator {
+        cleared: ::std::cell::Cell::new(false),
+    });
+    let service = CalculatorService(calculator.clone());
+
+    assert_eq!(
+        call_directly(&service, "Evaluate", "('2^10',)"),
+        Ok(String::from("('1024',)"))
+    );
+    assert_eq!(
+        call_directly(&service, "Evaluate", "('1 +',)"),
+        Err(MethodError::InvalidArgs(String::from(
+            "expected (, identifier or number"
+        )))
+    );
+    assert_eq!(
+        call_directly(&service, "ListVariables", "()"),
+        Ok(String::from(
+            "({'e': 2.7182818284590451, 'inf': inf, 'pi': 3.1415926535897931},)"
+        ))
+    );
+    assert_eq!(
+        call_directly(&service, "ClearHistory", "()"),
+        Ok(String::from("()"))
+    );
+    assert!(calculator.cleared.get());
+    assert_eq!(
+        call_directly(&service, "Quit", "()"),
+        Err(MethodError::UnknownMethod)
+    );
+}
+
+#[test]
+#[ignore = "needs dbus-daemon and gdbus, run with --ignored"]
+fn test_calculator_service() {
+    use super::dbus::{Export, TestBus};
+
+    let bus = TestBus::start();
+
+    let calculator = Rc::new(TestCalculator {
+        cleared: ::std::cell::Cell::new(false),
+    });
+    let service = Rc::new(CalculatorService(calculator.clone()));
+    let export = unsafe { Export::register(bus.connection, "/test", service).unwrap() };
+
+    let method = |name: &str| format!("com.github.moritzkn.ralculator.Calculator.{}", name);
+
+    assert_eq!(bus.call(&method("Evaluate"), &["'2^10'"]), "('1024',)");
+    // gdbus prints the error to stderr
+    assert_eq!(bus.call(&method("Evaluate"), &["'1 +'"]), "");
+    assert_eq!(
+        bus.call(&method("ListVariables"), &[]),
+        "({'e': 2.7182818284590451, 'inf': inf, 'pi': 3.1415926535897931},)"
+    );
+    assert_eq!(bus.call(&method("ClearHistory"), &[]), "()");
+    assert!(calculator.cleared.get());
+
+    drop(export);
+}
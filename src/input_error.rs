@@ -1,26 +1,131 @@
+use diagnostics::{explain_parse_error, find_identifier, suggest};
+use exec::{Context, ExecError, CONSTANT_NAMES, FUNCTION_NAMES};
 use parser::ParseError;
 use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
 use text_range::TextRange;
 
+#[derive(Debug, PartialEq, Clone)]
 pub struct InputError {
     /// The error message
     pub msg: String,
-    /// The columns in which the error occurred
+    /// The part of the input in which the error occurred
     pub pos: TextRange,
 }
 
 impl InputError {
-    pub fn from_parser_error(err: ParseError) -> InputError {
+    /// Common mistakes are explained, otherwise the error is at the
+    /// unexpected character, which is none at the end of the input
+    pub fn from_parser_error(err: ParseError, input: &str) -> InputError {
+        if let Some((msg, start, end)) = explain_parse_error(input, err.offset, &err.expected) {
+            return InputError {
+                msg,
+                pos: TextRange::new(input, start, end),
+            };
+        }
+
+        let end = match input[err.offset..].chars().next() {
+            Some(c) => err.offset + c.len_utf8(),
+            None => err.offset,
+        };
+
         InputError {
             msg: generate_expected_string(&err.expected),
-            pos: TextRange {
-                start: err.offset,
-                end: err.column,
-            },
+            pos: TextRange::new(input, err.offset, end),
+        }
+    }
+
+    /// Semantic errors are not tied to a position, so the whole input is
+    /// marked, except for unknown names which come with a suggestion
+    pub fn from_exec_error(err: &ExecError, input: &str, context: &Context) -> InputError {
+        let (name, suggestion) = match *err {
+            ExecError::UnknownIdentifier(ref name) => {
+                let variables = context.variables.keys().map(String::as_str);
+                let candidates = CONSTANT_NAMES.iter().cloned().chain(variables);
+                (name, suggest(name, candidates))
+            }
+            ExecError::UnknownFunction(ref name) => {
+                (name, suggest(name, FUNCTION_NAMES.iter().cloned()))
+            }
+            _ => {
+                return InputError {
+                    msg: err.to_string(),
+                    pos: TextRange::new(input, 0, input.len()),
+                }
+            }
+        };
+
+        let msg = match suggestion {
+            Some(suggestion) => format!("{}, did you mean {}?", err, suggestion),
+            None => err.to_string(),
+        };
+        let (start, end) = find_identifier(input, name).unwrap_or((0, input.len()));
+
+        InputError {
+            msg,
+            pos: TextRange::new(input, start, end),
         }
     }
 }
 
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.msg)
+    }
+}
+
+impl Error for InputError {}
+
+#[test]
+fn test_from_parser_error_unicode() {
+    use parser::parse;
+
+    let input = "π\u{00A0}× 2";
+    let err = InputError::from_parser_error(parse(input).unwrap_err(), input);
+    assert_eq!((err.pos.start.offset, err.pos.end.offset), (4, 6));
+    assert_eq!((err.pos.start.index, err.pos.end.index), (2, 3));
+    assert_eq!(err.pos.start.column, 2);
+    assert_eq!(err.pos.marker(input), "^");
+
+    let input = "1 +";
+    let err = InputError::from_parser_error(parse(input).unwrap_err(), input);
+    assert_eq!((err.pos.start.offset, err.pos.end.offset), (3, 3));
+}
+
+#[test]
+fn test_from_parser_error_multi_line() {
+    use parser::parse;
+
+    let input = "1 +\n2 * * 3";
+    let err = InputError::from_parser_error(parse(input).unwrap_err(), input);
+    assert_eq!((err.pos.start.line, err.pos.start.column), (2, 4));
+    assert_eq!(err.pos.start.line_text(input), "2 * * 3");
+}
+
+#[test]
+fn test_unknown_name_suggestions() {
+    use exec::parse_and_execute;
+
+    let mut context = Context::new();
+    context.variables.insert(String::from("radius"), 2.0);
+    let error = |input| parse_and_execute(input, &context).unwrap_err();
+
+    let err = error("2 * pu");
+    assert_eq!(err.msg, "unknown identifier pu, did you mean pi?");
+    assert_eq!((err.pos.start.offset, err.pos.end.offset), (4, 6));
+
+    let err = error("sni(30) + 1");
+    assert_eq!(err.msg, "unknown function sni, did you mean sin?");
+    assert_eq!((err.pos.start.offset, err.pos.end.offset), (0, 3));
+
+    assert_eq!(
+        error("2 * radus").msg,
+        "unknown identifier radus, did you mean radius?"
+    );
+    assert_eq!(error("foo").msg, "unknown identifier foo");
+}
+
 fn generate_expected_string(expected: &HashSet<&str>) -> String {
     let mut expected: Vec<&str> = expected.iter().copied().collect();
 
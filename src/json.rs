@@ -0,0 +1,508 @@
+use std::fmt;
+
+use ralculator::{AngleMode, InputError, Value};
+
+/// A JSON document, objects keep the order of their members
+#[derive(Debug, PartialEq, Clone)]
+pub enum Json {
+    Null,
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// JSON has no infinite numbers or NaN, these are written as `null`
+    fn number(n: f64) -> Json {
+        if n.is_finite() {
+            Json::Number(n)
+        } else {
+            Json::Null
+        }
+    }
+
+    #[cfg(any(test, feature = "gui"))]
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match *self {
+            Json::Object(ref members) => members
+                .iter()
+                .find(|member| member.0 == key)
+                .map(|member| &member.1),
+            _ => None,
+        }
+    }
+
+    #[cfg(any(test, feature = "gui"))]
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Json::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    #[cfg(any(test, feature = "gui"))]
+    pub fn as_usize(&self) -> Option<usize> {
+        match *self {
+            Json::Number(n) if n >= 0f64 && n.fract() == 0f64 => Some(n as usize),
+            _ => None,
+        }
+    }
+
+    /// Reading JSON is needed for the history of the window and to check
+    /// that the output round-trips
+    #[cfg(any(test, feature = "gui"))]
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut chars = text.chars().peekable();
+        let json = parse_value(&mut chars)?;
+        skip_whitespace(&mut chars);
+
+        match chars.next() {
+            None => Ok(json),
+            Some(c) => Err(format!("unexpected {:?} after the end of the document", c)),
+        }
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Json::Null => write!(f, "null"),
+            Json::Number(n) => write!(f, "{}", n),
+            Json::String(ref s) => write_string(f, s),
+            Json::Array(ref items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(ref members) => {
+                write!(f, "{{")?;
+                for (i, member) in members.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, &member.0)?;
+                    write!(f, ":{}", member.1)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+#[cfg(any(test, feature = "gui"))]
+type Input<'a> = ::std::iter::Peekable<::std::str::Chars<'a>>;
+
+#[cfg(any(test, feature = "gui"))]
+fn skip_whitespace(chars: &mut Input) {
+    while let Some(&c) = chars.peek() {
+        if !c.is_whitespace() {
+            break;
+        }
+        chars.next();
+    }
+}
+
+#[cfg(any(test, feature = "gui"))]
+fn expect(chars: &mut Input, expected: &str) -> Result<(), String> {
+    for c in expected.chars() {
+        if chars.next() != Some(c) {
+            return Err(format!("expected {}", expected));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(any(test, feature = "gui"))]
+fn parse_value(chars: &mut Input) -> Result<Json, String> {
+    skip_whitespace(chars);
+
+    match chars.peek().cloned() {
+        Some('n') => expect(chars, "null").map(|_| Json::Null),
+        Some('"') => parse_string(chars).map(Json::String),
+        Some('[') => {
+            chars.next();
+            let mut items = Vec::new();
+            skip_whitespace(chars);
+            if chars.peek() == Some(&']') {
+                chars.next();
+                return Ok(Json::Array(items));
+            }
+            loop {
+                items.push(parse_value(chars)?);
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => {}
+                    Some(']') => return Ok(Json::Array(items)),
+                    _ => return Err(String::from("expected , or ]")),
+                }
+            }
+        }
+        Some('{') => {
+            chars.next();
+            let mut members = Vec::new();
+            skip_whitespace(chars);
+            if chars.peek() == Some(&'}') {
+                chars.next();
+                return Ok(Json::Object(members));
+            }
+            loop {
+                skip_whitespace(chars);
+                let name = parse_string(chars)?;
+                skip_whitespace(chars);
+                expect(chars, ":")?;
+                members.push((name, parse_value(chars)?));
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => {}
+                    Some('}') => return Ok(Json::Object(members)),
+                    _ => return Err(String::from("expected , or }")),
+                }
+            }
+        }
+        Some(c) if c == '-' || c.is_ascii_digit() => {
+            let mut number = String::new();
+            while let Some(&c) = chars.peek() {
+                if !(c.is_ascii_digit() || "+-.eE".contains(c)) {
+                    break;
+                }
+                number.push(c);
+                chars.next();
+            }
+            number
+                .parse()
+                .map(Json::Number)
+                .map_err(|_| format!("invalid number {}", number))
+        }
+        Some(c) => Err(format!("unexpected {:?}", c)),
+        None => Err(String::from("unexpected end of the document")),
+    }
+}
+
+#[cfg(any(test, feature = "gui"))]
+fn parse_string(chars: &mut Input) -> Result<String, String> {
+    expect(chars, "\"")?;
+    let mut s = String::new();
+
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(s),
+            Some('\\') => match chars.next() {
+                Some('"') => s.push('"'),
+                Some('\\') => s.push('\\'),
+                Some('/') => s.push('/'),
+                Some('b') => s.push('\u{8}'),
+                Some('f') => s.push('\u{c}'),
+                Some('n') => s.push('\n'),
+                Some('r') => s.push('\r'),
+                Some('t') => s.push('\t'),
+                Some('u') => {
+                    let mut code = parse_hex(chars)?;
+                    // Characters outside the basic plane are written as surrogate pairs
+                    if (0xd800..0xdc00).contains(&code) {
+                        expect(chars, "\\u")?;
+                        let low = match parse_hex(chars)? {
+                            low @ 0xdc00..=0xdfff => low - 0xdc00,
+                            _ => return Err(String::from("invalid surrogate pair")),
+                        };
+                        code = 0x10000 + ((code - 0xd800) << 10) + low;
+                    }
+                    match ::std::char::from_u32(code) {
+                        Some(c) => s.push(c),
+                        None => return Err(String::from("invalid unicode escape")),
+                    }
+                }
+                _ => return Err(String::from("invalid escape sequence")),
+            },
+            Some(c) => s.push(c),
+            None => return Err(String::from("unterminated string")),
+        }
+    }
+}
+
+#[cfg(any(test, feature = "gui"))]
+fn parse_hex(chars: &mut Input) -> Result<u32, String> {
+    let digits: String = chars.take(4).collect();
+    u32::from_str_radix(&digits, 16).map_err(|_| String::from("invalid unicode escape"))
+}
+
+/// The JSON representation of evaluating one line of input
+#[derive(Debug, PartialEq, Clone)]
+pub struct Output {
+    pub line: usize,
+    pub input: String,
+    pub result: Result<Evaluated, Failure>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Evaluated {
+    /// `number`, `exact`, `expression`, `solutions`, `estimate` or `plot`
+    pub kind: String,
+    /// The value, its shape depends on `kind`
+    pub value: Json,
+    /// `deg`, `rad` or `grad` for angles, `None` for plain numbers
+    pub unit: Option<String>,
+    /// The result as it is shown in the calculator
+    pub formatted: String,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Failure {
+    pub message: String,
+    /// Start and end of the erroneous part of the input, in characters
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Failure {
+    pub fn new(err: InputError) -> Failure {
+        Failure {
+            message: err.msg,
+            start: err.pos.start.index,
+            end: err.pos.end.index,
+        }
+    }
+
+    pub fn to_json(&self) -> Json {
+        Json::Object(vec![
+            (String::from("message"), Json::String(self.message.clone())),
+            (String::from("start"), Json::Number(self.start as f64)),
+            (String::from("end"), Json::Number(self.end as f64)),
+        ])
+    }
+
+    #[cfg(any(test, feature = "gui"))]
+    pub fn from_json(json: &Json) -> Option<Failure> {
+        Some(Failure {
+            message: json.get("message")?.as_str()?.to_string(),
+            start: json.get("start")?.as_usize()?,
+            end: json.get("end")?.as_usize()?,
+        })
+    }
+}
+
+impl Output {
+    pub fn new(line: usize, input: &str, result: Result<Value, InputError>) -> Output {
+        let result = match result {
+            Ok(value) => Ok(Evaluated {
+                kind: String::from(kind(&value)),
+                value: json_value(&value),
+                unit: unit(&value).map(String::from),
+                formatted: value.to_string(),
+            }),
+            Err(err) => Err(Failure::new(err)),
+        };
+
+        Output {
+            line,
+            input: input.to_string(),
+            result,
+        }
+    }
+
+    pub fn to_json(&self) -> Json {
+        let mut members = vec![
+            (String::from("line"), Json::Number(self.line as f64)),
+            (String::from("input"), Json::String(self.input.clone())),
+        ];
+
+        match self.result {
+            Ok(ref evaluated) => {
+                members.push((String::from("type"), Json::String(evaluated.kind.clone())));
+                members.push((String::from("value"), evaluated.value.clone()));
+                members.push((
+                    String::from("unit"),
+                    evaluated
+                        .unit
+                        .as_ref()
+                        .map_or(Json::Null, |unit| Json::String(unit.clone())),
+                ));
+                members.push((
+                    String::from("formatted"),
+                    Json::String(evaluated.formatted.clone()),
+                ));
+            }
+            Err(ref failure) => {
+                members.push((String::from("type"), Json::String(String::from("error"))));
+                members.push((String::from("error"), failure.to_json()));
+            }
+        }
+
+        Json::Object(members)
+    }
+
+    #[cfg(test)]
+    pub fn from_json(json: &Json) -> Option<Output> {
+        let line = json.get("line")?.as_usize()?;
+        let input = json.get("input")?.as_str()?.to_string();
+
+        let result = match json.get("type")?.as_str()? {
+            "error" => Err(Failure::from_json(json.get("error")?)?),
+            kind => Ok(Evaluated {
+                kind: kind.to_string(),
+                value: json.get("value")?.clone(),
+                unit: match *json.get("unit")? {
+                    Json::Null => None,
+                    Json::String(ref unit) => Some(unit.clone()),
+                    _ => return None,
+                },
+                formatted: json.get("formatted")?.as_str()?.to_string(),
+            }),
+        };
+
+        Some(Output {
+            line,
+            input,
+            result,
+        })
+    }
+}
+
+fn kind(value: &Value) -> &'static str {
+    match *value {
+        Value::Number(_) | Value::Angle(..) => "number",
+        Value::Exact(_) => "exact",
+        Value::Expression(_) => "expression",
+        Value::Solutions(..) => "solutions",
+        Value::Estimate(..) => "estimate",
+        Value::Plot(_) => "plot",
+    }
+}
+
+fn json_value(value: &Value) -> Json {
+    match *value {
+        Value::Number(n) | Value::Angle(n, _) => Json::number(n),
+        Value::Exact(exact) => Json::number(exact.to_f64()),
+        Value::Expression(ref expression) => Json::String(expression.to_string()),
+        Value::Solutions(ref variable, ref solutions) => Json::Object(vec![
+            (String::from("variable"), Json::String(variable.clone())),
+            (
+                String::from("values"),
+                Json::Array(solutions.iter().map(|x| Json::number(*x)).collect()),
+            ),
+        ]),
+        Value::Estimate(value, error) => Json::Object(vec![
+            (String::from("value"), Json::number(value)),
+            (String::from("error"), Json::number(error)),
+        ]),
+        Value::Plot(_) => Json::Null,
+    }
+}
+
+fn unit(value: &Value) -> Option<&'static str> {
+    match *value {
+        Value::Angle(_, AngleMode::Degree) => Some("deg"),
+        Value::Angle(_, AngleMode::Radian) => Some("rad"),
+        Value::Angle(_, AngleMode::Gradian) => Some("grad"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+fn evaluate_to_json(input: &str) -> String {
+    let result = ralculator::parse_and_execute(input, &ralculator::Context::new());
+    Output::new(1, input, result).to_json().to_string()
+}
+
+#[cfg(test)]
+fn assert_round_trip(input: &str) {
+    let result = ralculator::parse_and_execute(input, &ralculator::Context::new());
+    let output = Output::new(7, input, result);
+    let json = Json::parse(&output.to_json().to_string()).unwrap();
+    assert_eq!(Output::from_json(&json), Some(output));
+}
+
+#[test]
+fn test_json_number() {
+    assert_eq!(
+        evaluate_to_json("2^10"),
+        r#"{"line":1,"input":"2^10","type":"number","value":1024,"unit":null,"formatted":"1024"}"#
+    );
+}
+
+#[test]
+fn test_json_angle_unit() {
+    assert_eq!(
+        evaluate_to_json("asin(1)"),
+        r#"{"line":1,"input":"asin(1)","type":"number","value":90,"unit":"deg","formatted":"90"}"#
+    );
+}
+
+#[test]
+fn test_json_angle_unit_of_calculations() {
+    assert!(evaluate_to_json("30deg + 10deg").contains(r#""value":40,"unit":"deg""#));
+    assert!(evaluate_to_json("2*asin(1)").contains(r#""value":180,"unit":"deg""#));
+    assert!(evaluate_to_json("sin(30deg)").contains(r#""unit":null"#));
+}
+
+#[test]
+fn test_json_solutions() {
+    assert_eq!(
+        evaluate_to_json("solve(x^2 = 4, x)"),
+        concat!(
+            r#"{"line":1,"input":"solve(x^2 = 4, x)","type":"solutions","#,
+            r#""value":{"variable":"x","values":[-2,2]},"unit":null,"#,
+            r#""formatted":"x = -2, x = 2"}"#
+        )
+    );
+}
+
+#[test]
+fn test_json_error() {
+    assert_eq!(
+        evaluate_to_json("foo \"bar\""),
+        concat!(
+            r#"{"line":1,"input":"foo \"bar\"","type":"error","#,
+            r#""error":{"message":"expected !, deg, grad, rad or °","start":4,"end":5}}"#
+        )
+    );
+}
+
+#[test]
+fn test_json_round_trip() {
+    assert_round_trip("1 / 3");
+    assert_round_trip("sin(30deg)");
+    assert_round_trip("diff(x^2, x)");
+    assert_round_trip("solve(x^2 - 5x + 6 = 0, x)");
+    assert_round_trip("integrate(x, x, 0, 1)");
+    assert_round_trip("plot(sin(x), 0, 1)");
+    assert_round_trip("2 +* 3");
+    assert_round_trip("\"π\\\t\u{1f600}");
+}
+
+#[test]
+fn test_json_parse() {
+    assert_eq!(
+        Json::parse(r#" { "a" : [1, -2.5e1, null], "b": "é😀\n" } "#),
+        Ok(Json::Object(vec![
+            (
+                String::from("a"),
+                Json::Array(vec![Json::Number(1f64), Json::Number(-25f64), Json::Null,])
+            ),
+            (String::from("b"), Json::String(String::from("é😀\n"))),
+        ]))
+    );
+    assert!(Json::parse("[1, 2").is_err());
+    assert!(Json::parse("{} x").is_err());
+}
@@ -0,0 +1,25 @@
+//! The calculation engine of ralculator, usable without the GTK application.
+//!
+//! ```
+//! use ralculator::{parse_and_execute, Context};
+//!
+//! let result = parse_and_execute("2^10", &Context::new()).unwrap();
+//! assert_eq!(result.to_string(), "1024");
+//! ```
+
+mod diagnostics;
+mod exec;
+mod input_error;
+mod parser;
+mod paths;
+mod text_range;
+
+pub use exec::{
+    diagnose, evaluate, is_expensive, parse_and_execute, simplify, value_range, AngleMode, Context,
+    Exact, ExecError, Plot, Value,
+};
+pub use input_error::InputError;
+pub use parser::ast;
+pub use parser::{parse, parse_recovering, ParseError};
+pub use paths::data_dir;
+pub use text_range::{display_width, Position, TextRange};
@@ -1,17 +1,134 @@
+#[cfg(feature = "gui")]
 #[macro_use]
 extern crate log;
+#[cfg(feature = "gui")]
 extern crate env_logger;
 
+#[cfg(feature = "gui")]
+extern crate cairo;
+#[cfg(feature = "gui")]
+extern crate gdk;
+#[cfg(feature = "gui")]
+extern crate gio;
+#[cfg(feature = "gui")]
+extern crate gio_sys;
+#[cfg(feature = "gui")]
 extern crate glib;
+#[cfg(feature = "gui")]
+extern crate glib_sys;
+#[cfg(feature = "gui")]
 extern crate gtk;
+extern crate libc;
+#[cfg(feature = "gui")]
+extern crate pango;
+extern crate ralculator;
 
-mod exec;
+mod args;
+mod batch;
+#[cfg(feature = "gui")]
 mod gui;
-mod input_error;
-mod parser;
-mod text_range;
+mod json;
+
+use std::env;
+use std::io;
+use std::process;
+
+use ralculator::Context;
 
 fn main() {
+    let args = match args::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(msg) => {
+            eprintln!("error: {}", msg);
+            eprintln!("Try `ralculator --help` for more information.");
+            process::exit(2);
+        }
+    };
+
+    if args.help {
+        print!("{}", args::HELP);
+        return;
+    }
+
+    if args.version {
+        println!("ralculator {}", env!("CARGO_PKG_VERSION"));
+        return;
+    }
+
+    let format = if args.json {
+        batch::Format::Json
+    } else {
+        batch::Format::Text
+    };
+
+    let context = Context::new();
+    let stdout = io::stdout();
+    let stderr = io::stderr();
+
+    let result = if !args.eval.is_empty() {
+        args.eval.iter().try_fold(true, |success, expression| {
+            batch::evaluate(
+                "-e",
+                1,
+                expression,
+                &context,
+                format,
+                &mut stdout.lock(),
+                &mut stderr.lock(),
+            )
+            .map(|evaluated| success && evaluated)
+        })
+    } else if !args.has_window_options() && stdin_is_redirected() {
+        let stdin = io::stdin();
+        batch::evaluate_lines(
+            "<stdin>",
+            stdin.lock(),
+            &context,
+            format,
+            &mut stdout.lock(),
+            &mut stderr.lock(),
+        )
+    } else {
+        launch_gui();
+        return;
+    };
+
+    match result {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }
+    }
+}
+
+/// The arguments are parsed again by the application, which might be an
+/// instance that is already running
+#[cfg(feature = "gui")]
+fn launch_gui() {
     env_logger::init().unwrap();
-    gui::launch();
+
+    let argv: Vec<String> = env::args().collect();
+    process::exit(gui::launch(&argv));
+}
+
+#[cfg(not(feature = "gui"))]
+fn launch_gui() {
+    eprintln!("error: ralculator was built without the GUI, use -e or pipe expressions into it");
+    process::exit(2);
+}
+
+/// Whether stdin is a file or a pipe, as opposed to a terminal or
+/// `/dev/null` when started from the desktop
+fn stdin_is_redirected() -> bool {
+    unsafe {
+        let mut stat: libc::stat = ::std::mem::zeroed();
+        if libc::fstat(libc::STDIN_FILENO, &mut stat) != 0 {
+            return false;
+        }
+
+        let file_type = stat.st_mode & libc::S_IFMT;
+        file_type == libc::S_IFIFO || file_type == libc::S_IFREG
+    }
 }
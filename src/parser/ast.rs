@@ -8,20 +8,111 @@ pub enum Root {
 #[derive(Debug, PartialEq, Clone)]
 pub enum Expression {
     Number(f64),
+    Identifier(String),
+    FunctionCall(String, Vec<Expression>),
     BinaryOperation(Box<Expression>, BinaryOperator, Box<Expression>),
     UnaryPrefixOperation(PrefixOperator, Box<Expression>),
     UnarySuffixOperation(Box<Expression>, SuffixOperator),
+    /// Only allowed as function argument, e.g. `solve(x^2 = 4, x)`
+    Equation(Box<Expression>, Box<Expression>),
+    /// A part of the input that couldn't be parsed, see `parse_recovering`.
+    /// It is written as the replacement character `�`.
+    Error,
+}
+
+impl Expression {
+    /// How tightly the expression binds when printed, operands that bind
+    /// less tightly than their parent are wrapped in parentheses.
+    fn precedence(&self) -> u8 {
+        match *self {
+            Expression::Equation(..) => 0,
+            Expression::Number(n) if n < 0f64 => 0,
+            Expression::UnaryPrefixOperation(..) => 0,
+            Expression::BinaryOperation(_, BinaryOperator::Plus, _)
+            | Expression::BinaryOperation(_, BinaryOperator::Minus, _) => 1,
+            Expression::BinaryOperation(ref left, BinaryOperator::Multiplication, ref right)
+                if is_implicit_multiplication(left, right) =>
+            {
+                3
+            }
+            Expression::BinaryOperation(_, BinaryOperator::Multiplication, _)
+            | Expression::BinaryOperation(_, BinaryOperator::Divide, _) => 2,
+            Expression::BinaryOperation(_, BinaryOperator::Power, _) => 4,
+            Expression::Number(_)
+            | Expression::Identifier(_)
+            | Expression::FunctionCall(..)
+            | Expression::UnarySuffixOperation(..)
+            | Expression::Error => 5,
+        }
+    }
+
+    fn fmt_operand(&self, f: &mut fmt::Formatter, min_precedence: u8) -> fmt::Result {
+        if self.precedence() < min_precedence {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
+}
+
+/// Products like `2x` or `3sin(x)` are printed without an operator
+fn is_implicit_multiplication(left: &Expression, right: &Expression) -> bool {
+    let factor = match *right {
+        Expression::BinaryOperation(ref base, BinaryOperator::Power, _) => base,
+        ref factor => factor,
+    };
+
+    match (left, factor) {
+        (&Expression::Number(n), &Expression::Identifier(_))
+        | (&Expression::Number(n), &Expression::FunctionCall(..)) => n >= 0f64,
+        _ => false,
+    }
 }
 
 impl fmt::Display for Expression {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Expression::Number(n) => write!(f, "{}", n),
+            Expression::Identifier(ref name) => write!(f, "{}", name),
+            Expression::FunctionCall(ref name, ref args) => {
+                write!(f, "{}(", name)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ")")
+            }
             Expression::BinaryOperation(ref left, op, ref right) => {
-                write!(f, "{} {} {}", left, op, right)
+                let precedence = self.precedence();
+
+                if op == BinaryOperator::Power {
+                    left.fmt_operand(f, precedence + 1)?;
+                    write!(f, "{}", op)?;
+                    right.fmt_operand(f, precedence)
+                } else if precedence == 3 {
+                    left.fmt_operand(f, precedence)?;
+                    right.fmt_operand(f, precedence + 1)
+                } else {
+                    left.fmt_operand(f, precedence)?;
+                    write!(f, " {} ", op)?;
+                    right.fmt_operand(f, precedence + 1)
+                }
+            }
+            Expression::UnaryPrefixOperation(ref op, ref exp) => {
+                write!(f, "{}", op)?;
+                exp.fmt_operand(f, 2)
             }
-            Expression::UnaryPrefixOperation(ref op, ref exp) => write!(f, "{}{}", op, exp),
-            Expression::UnarySuffixOperation(ref exp, ref op) => write!(f, "{}{}", exp, op),
+            Expression::Equation(ref left, ref right) => write!(f, "{} = {}", left, right),
+            Expression::UnarySuffixOperation(ref exp, ref op) => {
+                exp.fmt_operand(f, 5)?;
+                if op.is_word() {
+                    write!(f, " ")?;
+                }
+                write!(f, "{}", op)
+            }
+            Expression::Error => write!(f, "\u{FFFD}"),
         }
     }
 }
@@ -65,12 +156,29 @@ impl fmt::Display for PrefixOperator {
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum SuffixOperator {
     Factorial,
+    Degree,
+    Radian,
+    Gradian,
+}
+
+impl SuffixOperator {
+    /// Whether the operator is written as a word and needs to be separated
+    /// from its operand, e.g. `pi rad`
+    pub fn is_word(self) -> bool {
+        match self {
+            SuffixOperator::Factorial => false,
+            SuffixOperator::Degree | SuffixOperator::Radian | SuffixOperator::Gradian => true,
+        }
+    }
 }
 
 impl fmt::Display for SuffixOperator {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             SuffixOperator::Factorial => write!(f, "!"),
+            SuffixOperator::Degree => write!(f, "deg"),
+            SuffixOperator::Radian => write!(f, "rad"),
+            SuffixOperator::Gradian => write!(f, "grad"),
         }
     }
 }
@@ -1,8 +1,15 @@
 pub mod ast;
 
 mod grammar;
-pub use self::grammar::parse;
+mod recover;
 pub use self::grammar::ParseError;
+pub use self::recover::parse_recovering;
+
+use self::ast::Root;
+
+pub fn parse(input: &str) -> Result<Root, ParseError> {
+    grammar::parse(input, &[])
+}
 
 #[cfg(test)]
 mod test;
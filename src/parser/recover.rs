@@ -0,0 +1,183 @@
+//! Parsing that continues after errors, so all of them can be reported at
+//! once
+
+use std::iter;
+use std::ops::Range;
+
+use super::ast::{Expression, Root};
+use super::grammar::{parse, ParseError};
+
+/// Gives up on inputs with more errors than this
+const MAX_REPAIRS: usize = 64;
+/// Parsed as `Expression::Error`, but only where it was inserted
+const MARKER: &str = "\u{FFFD}";
+
+/// Parses the input like `parse`, but repairs it at every error and parses
+/// it again. Missing operands become `Expression::Error`, other mistakes are
+/// fixed or skipped. A `�` in the input itself is an error like any other
+/// unknown character. Returns the partial expression and all errors, with
+/// offsets into the original input.
+pub fn parse_recovering(input: &str) -> (Root, Vec<ParseError>) {
+    let mut text = input.to_string();
+    // The offset in `input` of every byte of `text` and of its end
+    let mut origins: Vec<usize> = (0..=input.len()).collect();
+    // The offsets in `text` of the inserted operands
+    let mut markers: Vec<usize> = Vec::new();
+    let mut errors: Vec<ParseError> = Vec::new();
+    // The parts of `input` that were deleted or replaced
+    let mut repaired: Vec<Range<usize>> = Vec::new();
+
+    for _ in 0..MAX_REPAIRS {
+        let err = match parse(&text, &markers) {
+            Ok(root) => return (root, errors),
+            Err(err) => err,
+        };
+
+        let offset = origins[err.offset];
+        // A repair can cause another error at the same place or right after
+        // it, e.g. `x = 3` becomes `x 3`
+        let is_follow_up = errors.last().map(|last| last.offset) == Some(offset)
+            || repaired
+                .iter()
+                .any(|span| span.start <= offset && offset <= skip_whitespace(input, span.end));
+        if !is_follow_up {
+            let (line, column) = line_and_column(input, offset);
+            errors.push(ParseError {
+                line,
+                column,
+                offset,
+                ..err.clone()
+            });
+        }
+
+        let (range, replacement) = match repair(&text, &err) {
+            Some(repair) => repair,
+            None => break,
+        };
+        let origin = origins[range.start];
+        if !range.is_empty() {
+            repaired.push(origin..origins[range.end]);
+        }
+        text.replace_range(range.clone(), replacement);
+        origins.splice(range.clone(), iter::repeat_n(origin, replacement.len()));
+
+        markers.retain(|marker| !range.contains(marker));
+        for marker in &mut markers {
+            if *marker >= range.end {
+                *marker = *marker - range.len() + replacement.len();
+            }
+        }
+        if replacement == MARKER {
+            markers.push(range.start);
+        }
+    }
+
+    (Root::Expression(Expression::Error), errors)
+}
+
+/// The part of the text to replace so that parsing gets past the error
+fn repair(text: &str, err: &ParseError) -> Option<(Range<usize>, &'static str)> {
+    let offset = err.offset;
+    let next = text[offset..].chars().next();
+    let expects_operand = ["number", "identifier", "("]
+        .iter()
+        .any(|token| err.expected.contains(token));
+
+    let repair = match next {
+        _ if expects_operand => (offset..offset, MARKER),
+        None if err.expected.contains(")") => (offset..offset, ")"),
+        None => return None,
+        Some(c) => {
+            let end = offset + c.len_utf8();
+
+            match c {
+                '×' | '·' => (offset..end, "*"),
+                '÷' => (offset..end, "/"),
+                // An operator is missing before the operand
+                c if c.is_ascii_alphanumeric() || c == '(' || c == 'π' || c == '∞' => {
+                    (offset..offset, "*")
+                }
+                _ => (offset..end, ""),
+            }
+        }
+    };
+
+    Some(repair)
+}
+
+/// The offset of the first character from `offset` on that isn't whitespace
+fn skip_whitespace(input: &str, offset: usize) -> usize {
+    input[offset..]
+        .find(|c: char| !c.is_whitespace())
+        .map_or(input.len(), |i| offset + i)
+}
+
+/// Like the line and column of errors from `parse`, both start at 1
+fn line_and_column(input: &str, offset: usize) -> (usize, usize) {
+    let before = &input[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.chars().rev().take_while(|&c| c != '\n').count() + 1;
+    (line, column)
+}
+
+#[cfg(test)]
+fn recover(input: &str) -> (String, Vec<usize>) {
+    let (Root::Expression(expression), errors) = parse_recovering(input);
+    let offsets = errors.iter().map(|err| err.offset).collect();
+    (expression.to_string(), offsets)
+}
+
+#[test]
+fn test_recovering_valid_input() {
+    assert_eq!(recover("1 + 2 * 3"), (String::from("1 + 2 * 3"), vec![]));
+}
+
+#[test]
+fn test_recovering_missing_operands() {
+    assert_eq!(recover("1 + * 2"), (String::from("1 + � * 2"), vec![4]));
+    assert_eq!(
+        recover("(1 +) * 2 +"),
+        (String::from("(1 + �) * 2 + �"), vec![4, 11])
+    );
+}
+
+#[test]
+fn test_recovering_typed_marker() {
+    assert_eq!(recover("1 + �"), (String::from("1 + �"), vec![4]));
+    assert_eq!(recover("� * 0"), (String::from("� * 0"), vec![0]));
+}
+
+#[test]
+fn test_recovering_operators() {
+    assert_eq!(
+        recover("2 × 3 ÷ 4"),
+        (String::from("2 * 3 / 4"), vec![2, 7])
+    );
+    assert_eq!(
+        recover("2 3 + 4 $"),
+        (String::from("2 * 3 + 4"), vec![2, 8])
+    );
+}
+
+#[test]
+fn test_recovering_without_follow_up_errors() {
+    assert_eq!(recover("x = 3"), (String::from("x * 3"), vec![2]));
+    assert_eq!(recover("2 ×"), (String::from("2 * �"), vec![2]));
+}
+
+#[test]
+fn test_recovering_parentheses() {
+    assert_eq!(recover("(1 + 2"), (String::from("1 + 2"), vec![6]));
+    assert_eq!(recover("1 + 2) * 3"), (String::from("1 + 2 * 3"), vec![5]));
+    assert_eq!(
+        recover("sin(1 + * 2"),
+        (String::from("sin(1 + � * 2)"), vec![8, 11])
+    );
+}
+
+#[test]
+fn test_recovering_positions() {
+    let (_, errors) = parse_recovering("1 +\n2 * * π +");
+    let positions: Vec<_> = errors.iter().map(|err| (err.line, err.column)).collect();
+    assert_eq!(positions, vec![(2, 5), (2, 10)]);
+}
@@ -400,20 +400,18 @@ fn test_nested_mixed_operations_white_spaces() {
 //         ))
 //     );
 // }
-//
-// #[test]
-// fn test_binary_and_unary_operations_factorial_suffix() {
-//     assert_eq!(
-//         parse("1! * 3"),
-//         Ok(Expression(
-//             BinaryOperation(
-//                 Box::new(UnarySuffixOperation(Box::new(Number(1f64)), Factorial)),
-//                 Multiplication,
-//                 Box::new(Number(3f64)),
-//             )
-//         ))
-//     );
-// }
+
+#[test]
+fn test_binary_and_unary_operations_factorial_suffix() {
+    assert_eq!(
+        parse("1! * 3"),
+        Ok(Expression(BinaryOperation(
+            Box::new(UnarySuffixOperation(Box::new(Number(1f64)), Factorial)),
+            Multiplication,
+            Box::new(Number(3f64)),
+        )))
+    );
+}
 
 #[test]
 fn test_parentheses_left_in_plus_expression() {
@@ -542,9 +540,165 @@ fn test_nested_parentheses() {
 //     );
 // }
 
+#[test]
+fn test_decimal_numbers() {
+    assert_eq!(parse("0.5"), Ok(Expression(Number(0.5f64))));
+    assert_eq!(parse("12.25"), Ok(Expression(Number(12.25f64))));
+    assert!(parse("1.").is_err());
+}
+
+#[test]
+fn test_identifiers() {
+    assert_eq!(parse("pi"), Ok(Expression(Identifier(String::from("pi")))));
+    assert_eq!(parse("π"), Ok(Expression(Identifier(String::from("π")))));
+    assert_eq!(
+        parse("x_1"),
+        Ok(Expression(Identifier(String::from("x_1"))))
+    );
+    assert!(parse("1x_").is_ok());
+}
+
+#[test]
+fn test_function_calls() {
+    assert_eq!(
+        parse("sin(1)"),
+        Ok(Expression(FunctionCall(
+            String::from("sin"),
+            vec![Number(1f64)]
+        )))
+    );
+    assert_eq!(
+        parse("f( 1 , 2 + 3 )"),
+        Ok(Expression(FunctionCall(
+            String::from("f"),
+            vec![
+                Number(1f64),
+                BinaryOperation(Box::new(Number(2f64)), Plus, Box::new(Number(3f64))),
+            ]
+        )))
+    );
+    assert_eq!(
+        parse("f()"),
+        Ok(Expression(FunctionCall(String::from("f"), vec![])))
+    );
+}
+
+#[test]
+fn test_function_calls_in_operations() {
+    assert_eq!(
+        parse("sin(1) + 2"),
+        Ok(Expression(BinaryOperation(
+            Box::new(FunctionCall(String::from("sin"), vec![Number(1f64)])),
+            Plus,
+            Box::new(Number(2f64)),
+        )))
+    );
+}
+
+#[test]
+fn test_implicit_multiplication() {
+    assert_eq!(
+        parse("2x"),
+        Ok(Expression(BinaryOperation(
+            Box::new(Number(2f64)),
+            Multiplication,
+            Box::new(Identifier(String::from("x"))),
+        )))
+    );
+    assert_eq!(
+        parse("2 (3)"),
+        Ok(Expression(BinaryOperation(
+            Box::new(Number(2f64)),
+            Multiplication,
+            Box::new(Number(3f64)),
+        )))
+    );
+}
+
+#[test]
+fn test_implicit_multiplication_precedence() {
+    assert_eq!(
+        parse("1 + 2x^3"),
+        Ok(Expression(BinaryOperation(
+            Box::new(Number(1f64)),
+            Plus,
+            Box::new(BinaryOperation(
+                Box::new(Number(2f64)),
+                Multiplication,
+                Box::new(BinaryOperation(
+                    Box::new(Identifier(String::from("x"))),
+                    Power,
+                    Box::new(Number(3f64)),
+                )),
+            )),
+        )))
+    );
+}
+
+#[test]
+fn test_missing_operator() {
+    assert!(parse("2 3").is_err());
+}
+
+#[test]
+fn test_angle_units() {
+    assert_eq!(
+        parse("30deg"),
+        Ok(Expression(UnarySuffixOperation(
+            Box::new(Number(30f64)),
+            Degree
+        )))
+    );
+    assert_eq!(
+        parse("30°"),
+        Ok(Expression(UnarySuffixOperation(
+            Box::new(Number(30f64)),
+            Degree
+        )))
+    );
+    assert_eq!(
+        parse("pi rad"),
+        Ok(Expression(UnarySuffixOperation(
+            Box::new(Identifier(String::from("pi"))),
+            Radian
+        )))
+    );
+    assert_eq!(
+        parse("100 grad"),
+        Ok(Expression(UnarySuffixOperation(
+            Box::new(Number(100f64)),
+            Gradian
+        )))
+    );
+}
+
+#[test]
+fn test_angle_units_in_operations() {
+    assert_eq!(
+        parse("30deg + 1"),
+        Ok(Expression(BinaryOperation(
+            Box::new(UnarySuffixOperation(Box::new(Number(30f64)), Degree)),
+            Plus,
+            Box::new(Number(1f64)),
+        )))
+    );
+}
+
+#[test]
+fn test_angle_unit_prefix_is_identifier() {
+    assert_eq!(
+        parse("2 degrees"),
+        Ok(Expression(BinaryOperation(
+            Box::new(Number(2f64)),
+            Multiplication,
+            Box::new(Identifier(String::from("degrees"))),
+        )))
+    );
+}
+
 #[test]
 fn test_simple_invalid_expression() {
-    assert!(parse("foo").is_err());
+    assert!(parse("$").is_err());
 }
 
 #[test]
@@ -593,3 +747,52 @@ fn test_error_detection() {
         assert_eq!(err.column, 5);
     }
 }
+
+fn print(input: &str) -> String {
+    match parse(input) {
+        Ok(Expression(exp)) => exp.to_string(),
+        Err(err) => panic!("failed to parse {}: {:?}", input, err),
+    }
+}
+
+#[test]
+fn test_print_keeps_required_parentheses() {
+    assert_eq!(print("(1 + 2) * 3"), "(1 + 2) * 3");
+    assert_eq!(print("1 - (2 - 3)"), "1 - (2 - 3)");
+    assert_eq!(print("(2^3)^4"), "(2^3)^4");
+    assert_eq!(print("(1 + 2)!"), "(1 + 2)!");
+}
+
+#[test]
+fn test_print_removes_redundant_parentheses() {
+    assert_eq!(print("1 + (2 * 3)"), "1 + 2 * 3");
+    assert_eq!(print("(1 - 2) - 3"), "1 - 2 - 3");
+    assert_eq!(print("2^(3^4)"), "2^3^4");
+}
+
+#[test]
+fn test_print_implicit_multiplication() {
+    assert_eq!(print("3 * x^2"), "3x^2");
+    assert_eq!(print("2 * sin(x)"), "2sin(x)");
+    assert_eq!(print("x * 2"), "x * 2");
+}
+
+#[test]
+fn test_print_prefix_and_suffix_operations() {
+    assert_eq!(print("-(1 + 2)"), "-(1 + 2)");
+    assert_eq!(print("2 * (-3)"), "2 * (-3)");
+    assert_eq!(print("pi rad"), "pi rad");
+}
+
+#[test]
+fn test_print_round_trip() {
+    for input in &[
+        "(1 + 2) * 3",
+        "2^(-1)",
+        "sin(2x) / (1 - x)",
+        "(-2)!",
+        "-(1 + 2)",
+    ] {
+        assert_eq!(parse(&print(input)), parse(input));
+    }
+}
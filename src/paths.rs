@@ -0,0 +1,37 @@
+//! Where ralculator keeps its files
+
+use std::env;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+/// `$XDG_DATA_HOME/ralculator`, falling back to `~/.local/share/ralculator`
+/// if `XDG_DATA_HOME` isn't set. `None` if neither variable is set.
+pub fn data_dir() -> Option<PathBuf> {
+    data_dir_from(env::var_os("XDG_DATA_HOME"), env::var_os("HOME"))
+}
+
+fn data_dir_from(xdg_data_home: Option<OsString>, home: Option<OsString>) -> Option<PathBuf> {
+    let data_home = match xdg_data_home {
+        Some(ref dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(home?).join(".local/share"),
+    };
+
+    Some(data_home.join("ralculator"))
+}
+
+#[test]
+fn test_data_dir() {
+    let dir = |xdg: Option<&str>, home: Option<&str>| {
+        data_dir_from(xdg.map(OsString::from), home.map(OsString::from))
+    };
+
+    assert_eq!(
+        dir(Some("/data"), Some("/home/a")),
+        Some(PathBuf::from("/data/ralculator"))
+    );
+    assert_eq!(
+        dir(Some(""), Some("/home/a")),
+        Some(PathBuf::from("/home/a/.local/share/ralculator"))
+    );
+    assert_eq!(dir(None, None), None);
+}
@@ -1,19 +1,190 @@
+use std::cmp;
+
+/// A position in the input
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Position {
+    /// The byte offset, for slicing the input
+    pub offset: usize,
+    /// The number of characters before the position, e.g. for placing cursors
+    pub index: usize,
+    /// The line, starting at 1
+    pub line: usize,
+    /// The column on a terminal, starting at 0. Wide characters take up two
+    /// columns and combining or invisible ones none.
+    pub column: usize,
+}
+
+impl Position {
+    /// The position at the byte `offset` of `input`
+    pub fn new(input: &str, offset: usize) -> Position {
+        let before = &input[..offset];
+        let line_start = line_start(input, offset);
+
+        Position {
+            offset,
+            index: before.chars().count(),
+            line: line_ends(input).take_while(|&end| end <= offset).count() + 1,
+            column: display_width(&before[line_start..]),
+        }
+    }
+
+    /// The line of `input` the position is in, without the line break
+    pub fn line_text<'a>(&self, input: &'a str) -> &'a str {
+        let line = &input[line_start(input, self.offset)..];
+
+        match line.find(|c| LINE_BREAKS.contains(&c)) {
+            Some(end) => &line[..end],
+            None => line,
+        }
+    }
+}
+
+/// A part of the input, e.g. the part in which an error occurred
+#[derive(Debug, PartialEq, Copy, Clone)]
 pub struct TextRange {
-    pub start: usize,
-    pub end: usize,
+    pub start: Position,
+    pub end: Position,
 }
 
 impl TextRange {
-    pub fn new(start: usize, end: usize) -> TextRange {
+    /// The part between the byte offsets `start` and `end` of `input`
+    pub fn new(input: &str, start: usize, end: usize) -> TextRange {
         TextRange {
-            start,
-            end: if end > start { end } else { start },
+            start: Position::new(input, start),
+            end: Position::new(input, cmp::max(start, end)),
         }
     }
-    pub fn len(&self) -> usize {
-        self.end - self.start
+
+    /// The columns the range takes up on a terminal, a range spanning
+    /// multiple lines is cut off at the end of its first line
+    pub fn width(&self, input: &str) -> usize {
+        let end = if self.end.line == self.start.line {
+            self.end.column
+        } else {
+            display_width(self.start.line_text(input))
+        };
+
+        end - self.start.column
+    }
+
+    /// Carets to print under the range, at least one so errors at the end
+    /// of the input are visible
+    pub fn marker(&self, input: &str) -> String {
+        "^".repeat(cmp::max(self.width(input), 1))
     }
-    pub fn fill(&self, with: char) -> String {
-        (with.to_string()).repeat(self.len())
+}
+
+/// The line breaks of the grammar, `\r\n` counts as one
+static LINE_BREAKS: [char; 4] = ['\n', '\r', '\u{2028}', '\u{2029}'];
+
+/// The byte offsets after each line break
+fn line_ends<'a>(text: &'a str) -> impl Iterator<Item = usize> + 'a {
+    text.char_indices().filter_map(move |(i, c)| match c {
+        '\r' if text[i + 1..].starts_with('\n') => None,
+        c if LINE_BREAKS.contains(&c) => Some(i + c.len_utf8()),
+        _ => None,
+    })
+}
+
+fn line_start(text: &str, offset: usize) -> usize {
+    line_ends(text)
+        .take_while(|&end| end <= offset)
+        .last()
+        .unwrap_or(0)
+}
+
+/// The columns a terminal uses to show the text
+pub fn display_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+fn char_width(c: char) -> usize {
+    match c as u32 {
+        // Combining marks, zero width spaces and joiners, variation selectors
+        // and the byte order mark
+        0x0300..=0x036F | 0x180E | 0x200B..=0x200F | 0x2060..=0x2064 | 0xFE00..=0xFE0F | 0xFEFF => {
+            0
+        }
+        // East Asian wide and fullwidth characters, including the ideographic
+        // space, and emoji
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x3FFFD => 2,
+        _ => 1,
     }
 }
+
+#[test]
+fn test_position_ascii() {
+    assert_eq!(
+        Position::new("1 + 2", 4),
+        Position {
+            offset: 4,
+            index: 4,
+            line: 1,
+            column: 4,
+        }
+    );
+}
+
+#[test]
+fn test_position_unicode() {
+    // π and × take two bytes, the non-breaking space too
+    let input = "2π\u{00A0}× x";
+    assert_eq!(
+        Position::new(input, 8),
+        Position {
+            offset: 8,
+            index: 5,
+            line: 1,
+            column: 5,
+        }
+    );
+}
+
+#[test]
+fn test_position_wide_and_invisible() {
+    let input = "1\u{3000}+\u{FEFF}x";
+    assert_eq!(Position::new(input, 4).column, 3);
+    assert_eq!(Position::new(input, 8).column, 4);
+}
+
+#[test]
+fn test_position_multi_line() {
+    let input = "1 +\r\n2 *\u{2028}π\rx";
+    let position = Position::new(input, 13);
+    assert_eq!((position.line, position.column), (3, 1));
+    assert_eq!(position.line_text(input), "π");
+
+    let position = Position::new(input, 15);
+    assert_eq!((position.line, position.column), (4, 1));
+    assert_eq!(position.line_text(input), "x");
+
+    assert_eq!(Position::new(input, 5).line, 2);
+    assert_eq!(Position::new(input, 5).line_text(input), "2 *");
+}
+
+#[test]
+fn test_marker() {
+    let input = "π × foo";
+    let range = TextRange::new(input, 6, 9);
+    assert_eq!(range.start.column, 4);
+    assert_eq!(range.marker(input), "^^^");
+
+    // The end of the input
+    assert_eq!(TextRange::new(input, 9, 9).marker(input), "^");
+
+    // Up to the end of the first line
+    assert_eq!(TextRange::new("1 +\n2", 2, 5).marker("1 +\n2"), "^");
+}